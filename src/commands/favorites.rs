@@ -0,0 +1,101 @@
+use serenity::client::Context;
+
+use anyhow::Context as AnyhowCtx;
+use anyhow::Result;
+use log::error;
+use serenity::framework::standard::{
+    macros::{command, hook},
+    Args, CommandResult,
+};
+use serenity::model::channel::Message;
+
+use crate::utils::discord::get_channel_of_member;
+use crate::utils::discord::join_channel;
+use crate::utils::discord::play_from_file;
+use crate::utils::error::check_msg;
+use crate::utils::error::handle_error;
+use crate::utils::favorites::get_quick_slot;
+use crate::utils::history::{PlayInterface, PlayOrigin};
+
+/// Highest quick slot reachable through the `!1`..`!9` shortcuts
+const MAX_SHORTCUT_SLOT: usize = 9;
+
+/// Plays the sound in one of your quick slots. Add favorites with `/fav add` to fill them.
+/// Usage: `!q [slot]` or `!1`..`!9`
+#[command]
+#[only_in(guilds)]
+#[aliases(quick)]
+pub async fn q(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let slot = match args.single::<usize>() {
+        Ok(slot) => slot,
+        Err(_) => {
+            let err = "Must provide the number of a quick slot";
+            check_msg(msg.channel_id.say(&ctx.http, err).await);
+
+            return Err(handle_error(err.to_string()).into());
+        }
+    };
+
+    play_quick_slot(ctx, msg, slot).await?;
+
+    Ok(())
+}
+
+/// Turns unknown commands like `!3` into plays of the matching quick slot
+#[hook]
+pub async fn quick_slot_shortcut(ctx: &Context, msg: &Message, unknown_command_name: &str) {
+    let slot = match unknown_command_name.parse::<usize>() {
+        Ok(slot) if (1..=MAX_SHORTCUT_SLOT).contains(&slot) => slot,
+        _ => return,
+    };
+
+    if let Err(err) = play_quick_slot(ctx, msg, slot).await {
+        error!("Error playing quick slot {}: {}", slot, err);
+    }
+}
+
+async fn play_quick_slot(ctx: &Context, msg: &Message, slot: usize) -> Result<()> {
+    let guild = msg
+        .guild(&ctx.cache)
+        .await
+        .ok_or_else(|| handle_error("No guild present in cache".to_string()))?;
+
+    let channel = msg
+        .channel(&ctx.cache)
+        .await
+        .ok_or_else(|| handle_error("Missing channel in cache".to_string()))?;
+
+    let guild_channel = channel
+        .guild()
+        .ok_or_else(|| handle_error("Channel not in guild".to_string()))?;
+
+    if !guild_channel.name().eq("pascal-phone") {
+        return Ok(());
+    }
+
+    let sound_name = match get_quick_slot(ctx, msg.author.id, slot).await? {
+        Some(sound_name) => sound_name,
+        None => {
+            check_msg(
+                msg.reply(
+                    ctx,
+                    format!("Quick slot {} is empty. Use `/fav add` to fill it", slot),
+                )
+                .await,
+            );
+            return Ok(());
+        }
+    };
+
+    let user_voice_channel_id = get_channel_of_member(ctx.clone(), guild.id, msg.author.id).await;
+
+    match user_voice_channel_id {
+        Some(channel) => join_channel(ctx, guild.id, channel)
+            .await
+            .with_context(|| handle_error("Failed to join channel".to_string()))?,
+        None => check_msg(msg.reply(ctx, "Not in a voice channel").await),
+    };
+
+    let origin = PlayOrigin::new(msg.author.id, PlayInterface::TextCommand);
+    play_from_file(ctx, msg.channel_id, guild.id, &sound_name, origin).await
+}
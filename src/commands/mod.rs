@@ -1,13 +1,17 @@
+use crate::commands::favorites::Q_COMMAND;
 use crate::commands::list::LIST_COMMAND;
 use crate::commands::play::PLAY_COMMAND;
+use crate::commands::random::RANDOM_COMMAND;
 use crate::commands::stop::STOP_COMMAND;
 use serenity::framework::standard::macros::group;
 
+pub mod favorites;
 pub mod help;
 pub mod list;
 pub mod play;
+pub mod random;
 pub mod stop;
 
 #[group]
-#[commands(play, list, stop)]
+#[commands(play, list, stop, random, q)]
 struct General;
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use serenity::client::Context;
 
 use anyhow::Context as AnyhowCtx;
@@ -7,16 +9,26 @@ use serenity::model::channel::Message;
 
 use crate::utils::discord::get_channel_of_member;
 use crate::utils::discord::join_channel;
-use crate::utils::discord::play_from_file;
-use crate::utils::discord::play_youtube;
+use crate::utils::discord::play_from_file_with_options;
+use crate::utils::discord::play_url;
+use crate::utils::effects::{split_flags, AudioOptions};
 use crate::utils::error::check_msg;
 use crate::utils::error::handle_error;
+use crate::utils::history::{PlayInterface, PlayOrigin};
+use crate::utils::sequence::{parse_steps, play_sequence};
+use crate::utils::url_source::is_url;
 
+/// Plays a sound, a saved combo or a URL.
+/// Several sounds separated by commas are played back to back, durations like `500ms`
+/// or `1.5s` in between them add a pause.
+/// Effects are added with flags like `--pitch 1.5`, `--speed 0.8` or `--echo`,
+/// `--start 0:12` and `--end 0:20` only play part of the sound or URL.
+/// Usage: `!play [sound name], [sound name or pause], ... [--flags]`
 #[command]
 #[only_in(guilds)]
 #[aliases(p)]
 pub async fn play(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let arg = parse_argument(ctx, msg, &mut args).await?;
+    let (mut arguments, options) = parse_arguments(ctx, msg, &mut args).await?;
 
     let guild = msg
         .guild(&ctx.cache)
@@ -47,26 +59,70 @@ pub async fn play(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
         None => check_msg(msg.reply(ctx, "Not in a voice channel").await),
     };
 
-    if arg.starts_with("https://") {
-        play_youtube(ctx, msg.channel_id, guild.id, &arg).await?;
+    let origin = PlayOrigin::new(msg.author.id, PlayInterface::TextCommand);
+    if arguments.len() > 1 {
+        let steps = match parse_steps(&arguments) {
+            Ok(steps) => steps,
+            Err(reason) => {
+                check_msg(msg.reply(ctx, reason).await);
+                return Ok(());
+            }
+        };
+        play_sequence(
+            ctx,
+            msg.channel_id,
+            guild.id,
+            steps,
+            Duration::ZERO,
+            origin,
+            options,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let arg = arguments.remove(0);
+    if is_url(&arg) {
+        play_url(ctx, msg.channel_id, guild.id, &arg, origin, &options).await?;
     } else {
-        play_from_file(ctx, msg.channel_id, guild.id, &arg).await?;
+        play_from_file_with_options(ctx, msg.channel_id, guild.id, &arg, origin, &options).await?;
     }
 
     Ok(())
 }
 
-/// Validates given sound name and responds to errors with a feedback message
-async fn parse_argument(ctx: &Context, msg: &Message, args: &mut Args) -> Result<String> {
-    let sound_name = match args.single::<String>() {
-        Ok(sound_name) => sound_name,
-        Err(_) => {
-            let err = "Must provide name of the sound to play or a Youtube URL";
-            check_msg(msg.channel_id.say(&ctx.http, err).await);
+/// Validates given sound names and effect flags and responds to errors with a feedback message
+async fn parse_arguments(
+    ctx: &Context,
+    msg: &Message,
+    args: &mut Args,
+) -> Result<(Vec<String>, AudioOptions)> {
+    let mut arguments = Vec::new();
+    let mut options = AudioOptions::default();
 
-            return Err(handle_error(err.to_string()));
+    for arg in args.iter::<String>().filter_map(|arg| arg.ok()) {
+        let (name, flags) = split_flags(&arg);
+
+        match AudioOptions::parse_flags(flags) {
+            Ok(parsed) => options.merge(parsed),
+            Err(err) => {
+                check_msg(msg.channel_id.say(&ctx.http, &err).await);
+
+                return Err(handle_error(err));
+            }
         }
-    };
 
-    Ok(sound_name)
+        if !name.is_empty() {
+            arguments.push(name.to_owned());
+        }
+    }
+
+    if arguments.is_empty() {
+        let err = "Must provide name of the sound to play or a URL";
+        check_msg(msg.channel_id.say(&ctx.http, err).await);
+
+        return Err(handle_error(err.to_string()));
+    }
+
+    Ok((arguments, options))
 }
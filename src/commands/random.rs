@@ -0,0 +1,73 @@
+use serenity::client::Context;
+
+use anyhow::Context as AnyhowCtx;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::channel::Message;
+
+use crate::utils::discord::get_channel_of_member;
+use crate::utils::discord::join_channel;
+use crate::utils::discord::play_from_file;
+use crate::utils::error::check_msg;
+use crate::utils::error::handle_error;
+use crate::utils::history::{PlayInterface, PlayOrigin};
+use crate::utils::random::pick_random_sound;
+
+/// Plays a random sound. An optional filter restricts the pick to a category
+/// or to sounds containing the filter in their name.
+/// Usage: `!random [filter]`
+#[command]
+#[only_in(guilds)]
+#[aliases(r)]
+pub async fn random(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let filter = args.rest().trim();
+    let filter = if filter.is_empty() {
+        None
+    } else {
+        Some(filter)
+    };
+
+    let guild = msg
+        .guild(&ctx.cache)
+        .await
+        .ok_or_else(|| handle_error("No guild present in cache".to_string()))?;
+
+    let channel = msg
+        .channel(&ctx.cache)
+        .await
+        .ok_or_else(|| handle_error("Missing channel in cache".to_string()))?;
+
+    let guild_channel = channel
+        .guild()
+        .ok_or_else(|| handle_error("Channel not in guild".to_string()))?;
+
+    if !guild_channel.name().eq("pascal-phone") {
+        return Ok(());
+    }
+
+    let sound_name = match pick_random_sound(ctx, guild.id, filter).await? {
+        Some(sound_name) => sound_name,
+        None => {
+            check_msg(msg.reply(ctx, "No sounds match that filter").await);
+            return Ok(());
+        }
+    };
+
+    let user_voice_channel_id = get_channel_of_member(ctx.clone(), guild.id, msg.author.id).await;
+
+    match user_voice_channel_id {
+        Some(channel) => join_channel(ctx, guild.id, channel)
+            .await
+            .with_context(|| handle_error("Failed to join channel".to_string()))?,
+        None => check_msg(msg.reply(ctx, "Not in a voice channel").await),
+    };
+
+    check_msg(
+        msg.channel_id
+            .say(&ctx.http, format!("🎲 Playing **{}**", sound_name))
+            .await,
+    );
+    let origin = PlayOrigin::new(msg.author.id, PlayInterface::TextCommand);
+    play_from_file(ctx, msg.channel_id, guild.id, &sound_name, origin).await?;
+
+    Ok(())
+}
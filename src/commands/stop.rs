@@ -4,6 +4,7 @@ use serenity::{
     model::channel::Message,
 };
 
+use crate::utils::discord::stop_playback;
 use crate::utils::error::handle_error;
 
 #[command]
@@ -27,19 +28,7 @@ pub async fn stop(ctx: &Context, msg: &Message) -> CommandResult {
         return Ok(());
     }
 
-    let manager = songbird::get(ctx)
-        .await
-        .ok_or_else(|| {
-            handle_error("Songbird Voice client placed in at initialisation.".to_string())
-        })?
-        .clone();
-
-    let handler_lock = manager
-        .get(guild.id)
-        .ok_or_else(|| handle_error("Couldn't get handler lock".to_string()))?;
-    let mut handler = handler_lock.lock().await;
-
-    handler.stop();
+    stop_playback(ctx, guild.id).await?;
 
     Ok(())
 }
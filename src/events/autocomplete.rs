@@ -2,7 +2,9 @@ use log::error;
 use serde_json::Value;
 use serenity::{client::Context, model::interactions::autocomplete::AutocompleteInteraction};
 
-use crate::utils::fuzzy_lookup;
+use crate::utils::{
+    combos::get_combos, favorites::get_favorites, fuzzy_lookup, stats::get_play_counts,
+};
 
 use super::slash_commands::PLAY_COMMAND;
 
@@ -29,7 +31,7 @@ pub async fn handle_autocomplete_interaction(ctx: Context, autocomplete: Autocom
             None => return, // Whatever the hell arrives here, we don't want anything to do with it
         };
 
-        let sound_files: Vec<String> = match crate::utils::sound_files::get_sound_files() {
+        let mut sound_files: Vec<String> = match crate::utils::sound_files::get_sound_files() {
             Ok(map) => map.keys().map(|key| key.to_owned()).collect(),
             Err(e) => {
                 error!(
@@ -40,7 +42,48 @@ pub async fn handle_autocomplete_interaction(ctx: Context, autocomplete: Autocom
             }
         };
 
-        let suggestions = fuzzy_lookup::get_lookup_results(searched_sound, sound_files);
+        // Saved combos play like sounds, so suggest them as well
+        if let Some(guild_id) = autocomplete.guild_id {
+            match get_combos(&ctx, guild_id).await {
+                Ok(combos) => sound_files.extend(combos.into_iter().map(|(name, _)| name)),
+                Err(e) => error!("[Autocomplete Interaction] Error fetching combos: {}", e),
+            }
+        }
+
+        // The caller's favorites that still exist and match what they typed go first
+        let favorites: Vec<String> = match get_favorites(&ctx, autocomplete.user.id).await {
+            Ok(favorites) => favorites
+                .into_iter()
+                .filter(|favorite| sound_files.contains(favorite))
+                .collect(),
+            Err(e) => {
+                error!("[Autocomplete Interaction] Error fetching favorites: {}", e);
+                Vec::new()
+            }
+        };
+        let favorite_hits = if searched_sound.is_empty() {
+            favorites
+        } else {
+            fuzzy_lookup::get_lookup_results(searched_sound, favorites)
+        };
+
+        let suggestions = match autocomplete.guild_id {
+            // Nothing typed yet, so suggest the guild's most played sounds
+            Some(guild_id) if searched_sound.is_empty() => {
+                match get_play_counts(&ctx, guild_id).await {
+                    Ok(usage) => fuzzy_lookup::get_ranked_results(sound_files, &usage),
+                    Err(e) => {
+                        error!(
+                            "[Autocomplete Interaction] Error fetching play counts: {}",
+                            e
+                        );
+                        fuzzy_lookup::get_lookup_results(searched_sound, sound_files)
+                    }
+                }
+            }
+            _ => fuzzy_lookup::get_lookup_results(searched_sound, sound_files),
+        };
+        let suggestions = fuzzy_lookup::prepend_favorites(favorite_hits, suggestions);
 
         if let Err(e) = autocomplete
             .create_autocomplete_response(&ctx.http, |response| {
@@ -0,0 +1,96 @@
+use log::error;
+use serenity::{
+    client::Context,
+    model::interactions::{
+        message_component::MessageComponentInteraction, InteractionResponseType,
+    },
+};
+
+use crate::utils::{
+    discord::{enqueue_url, get_channel_of_member, join_channel, play_from_file},
+    history::{PlayInterface, PlayOrigin},
+    soundboard::PANEL_BUTTON_PREFIX,
+    youtube_search::SEARCH_MENU_ID,
+};
+
+pub async fn handle_component_interaction(ctx: Context, component: MessageComponentInteraction) {
+    // Acknowledge the interaction right away, queueing a pick can take a moment
+    if let Err(e) = component
+        .create_interaction_response(&ctx.http, |response| {
+            response.kind(InteractionResponseType::DeferredUpdateMessage)
+        })
+        .await
+    {
+        error!("Error responding to component interaction: {}", e);
+    }
+
+    if let Some(sound_name) = component.data.custom_id.strip_prefix(PANEL_BUTTON_PREFIX) {
+        handle_soundboard_button(&ctx, &component, sound_name).await;
+    } else if component.data.custom_id == SEARCH_MENU_ID {
+        handle_search_pick(&ctx, &component).await;
+    }
+}
+
+async fn handle_search_pick(ctx: &Context, component: &MessageComponentInteraction) {
+    let (guild_id, url) = match (component.guild_id, component.data.values.first()) {
+        (Some(guild_id), Some(url)) => (guild_id, url),
+        _ => return,
+    };
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, component.user.id).await
+    {
+        if let Err(e) = join_channel(ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    let origin = PlayOrigin::new(component.user.id, PlayInterface::SlashCommand);
+    let content = match enqueue_url(ctx, guild_id, url, origin).await {
+        Ok(_) => format!("Queued <{}>", url),
+        Err(e) => {
+            error!("Failed to queue search result {}: {}", url, e);
+            format!("Could not queue <{}>", url)
+        }
+    };
+
+    // Replace the menu so a result can't be queued twice by accident
+    if let Err(e) = component
+        .edit_original_interaction_response(&ctx.http, |response| {
+            response
+                .content(content)
+                .components(|components| components)
+        })
+        .await
+    {
+        error!("Error updating Youtube search results: {}", e);
+    }
+}
+
+async fn handle_soundboard_button(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    sound_name: &str,
+) {
+    let guild_id = match component.guild_id {
+        Some(gid) => gid,
+        None => {
+            error!(
+                "Guild ID not found for soundboard button {} and caller {}",
+                sound_name, component.user.name
+            );
+            return;
+        }
+    };
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, component.user.id).await
+    {
+        if let Err(e) = join_channel(ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    let origin = PlayOrigin::new(component.user.id, PlayInterface::Soundboard);
+    if let Err(e) = play_from_file(ctx, component.channel_id, guild_id, sound_name, origin).await {
+        error!("Failed to play sound from soundboard: {}", e);
+    }
+}
@@ -1,7 +1,15 @@
 use super::{
-    autocomplete::handle_autocomplete_interaction, slash_commands::handle_slash_commands,
+    autocomplete::handle_autocomplete_interaction,
+    components::handle_component_interaction,
+    quiz::handle_quiz_message,
+    reactions::handle_reaction_add,
+    slash_commands::{handle_slash_commands, register_commands},
+    triggers::handle_trigger_message,
     voice::handle_voice_state_update,
 };
+use crate::utils::{
+    idle::start_idle_watcher, schedule::start_scheduler, soundboard::start_library_watcher,
+};
 use log::error;
 use serenity::{
     async_trait,
@@ -9,10 +17,7 @@ use serenity::{
     model::{
         channel::Message,
         id::GuildId,
-        interactions::{
-            application_command::{ApplicationCommand, ApplicationCommandOptionType},
-            Interaction,
-        },
+        interactions::{application_command::ApplicationCommand, Interaction},
         prelude::*,
     },
 };
@@ -30,7 +35,15 @@ impl EventHandler for Handler {
             if let Err(why) = msg.channel_id.say(&ctx.http, "Pong!").await {
                 println!("Error sending message: {:?}", why);
             }
+            return;
+        }
+
+        // Quiz answers shouldn't set off triggers as well
+        if handle_quiz_message(&ctx, &msg).await {
+            return;
         }
+
+        handle_trigger_message(ctx, msg).await;
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
@@ -38,9 +51,16 @@ impl EventHandler for Handler {
             handle_slash_commands(ctx, command).await;
         } else if let Interaction::Autocomplete(autocomplete) = interaction {
             handle_autocomplete_interaction(ctx, autocomplete).await;
+        } else if let Interaction::MessageComponent(component) = interaction {
+            handle_component_interaction(ctx, component).await;
         }
     }
 
+    // Plays the sound mapped to the emoji someone reacted with
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        handle_reaction_add(ctx, add_reaction).await;
+    }
+
     // Detects when a known user joins an allowed channel and plays a registered intro song
     async fn voice_state_update(
         &self,
@@ -53,27 +73,16 @@ impl EventHandler for Handler {
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
-        match ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
-            commands.create_application_command(|command| {
-                command
-                    .name("play")
-                    .description("Command Pascal to play a sound")
-                    .create_option(|option| {
-                        option
-                            .name("sound")
-                            .description("Name of the sound to play. Use the list command to see all possible values")
-                            .kind(ApplicationCommandOptionType::String)
-                            .required(true)
-                            .set_autocomplete(true)
-                    })
-            })
-        })
-        .await
+        if let Err(e) =
+            ApplicationCommand::set_global_application_commands(&ctx.http, register_commands).await
         {
-            Ok(_) => (),
-            Err(e) => error!("Error registering slash commands: {}", e),
+            error!("Error registering slash commands: {}", e);
         }
 
+        start_library_watcher(ctx.clone());
+        start_idle_watcher(ctx.clone());
+        start_scheduler(ctx.clone());
+
         println!("{} is connected!", ready.user.name);
     }
 }
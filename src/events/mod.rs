@@ -1,4 +1,8 @@
 mod autocomplete;
+mod components;
 pub mod handler;
+mod quiz;
+mod reactions;
 mod slash_commands;
+mod triggers;
 mod voice;
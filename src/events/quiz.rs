@@ -0,0 +1,31 @@
+use log::error;
+use serenity::{client::Context, model::channel::Message};
+
+use crate::utils::{error::check_msg, quiz::submit_answer};
+
+/// Checks the message as a guess in a running quiz. Returns true if it was a correct guess.
+pub async fn handle_quiz_message(ctx: &Context, msg: &Message) -> bool {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return false,
+    };
+    if msg.author.bot {
+        return false;
+    }
+
+    match submit_answer(ctx, guild_id, msg.channel_id, msg.author.id, &msg.content).await {
+        Ok(Some(solution)) => {
+            let message = format!(
+                "✅ <@{}> got it, it's **{}**! +{} points",
+                msg.author.id, solution.sound, solution.points
+            );
+            check_msg(msg.channel_id.say(&ctx.http, message).await);
+            true
+        }
+        Ok(None) => false,
+        Err(err) => {
+            error!("Error checking quiz answer: {}", err);
+            false
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use log::error;
+use serenity::{client::Context, model::channel::Reaction};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel, play_from_file_with_options},
+    effects::AudioOptions,
+    history::{PlayInterface, PlayOrigin},
+    reactions::{emoji_key, get_reaction_sound},
+};
+
+/// Plays the sound mapped to the emoji in the reactor's voice channel
+pub async fn handle_reaction_add(ctx: Context, reaction: Reaction) {
+    let (guild_id, user_id) = match (reaction.guild_id, reaction.user_id) {
+        (Some(guild_id), Some(user_id)) => (guild_id, user_id),
+        _ => return,
+    };
+
+    let key = match emoji_key(&reaction.emoji) {
+        Some(key) => key,
+        None => return,
+    };
+
+    let sound = match get_reaction_sound(&ctx, guild_id, &key).await {
+        Ok(Some(sound)) => sound,
+        Ok(None) => return,
+        Err(err) => {
+            error!("Error looking up reaction sound: {}", err);
+            return;
+        }
+    };
+
+    if ctx.cache.user(user_id).await.map_or(false, |user| user.bot) {
+        return;
+    }
+
+    let channel_id = match get_channel_of_member(ctx.clone(), guild_id, user_id).await {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    if let Err(err) = join_channel(&ctx, guild_id, channel_id).await {
+        error!("Failed to join channel: {}", err);
+    }
+
+    let origin = PlayOrigin::new(user_id, PlayInterface::Reaction);
+    if let Err(err) = play_from_file_with_options(
+        &ctx,
+        reaction.channel_id,
+        guild_id,
+        &sound,
+        origin,
+        &AudioOptions::default(),
+    )
+    .await
+    {
+        error!("Error playing reaction sound {}: {}", sound, err);
+    }
+}
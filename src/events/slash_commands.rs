@@ -1,14 +1,15 @@
 use std::fmt;
 
-use anyhow::Context as AnyhowCtx;
 use log::error;
 use serenity::{
+    builder::{CreateApplicationCommandOption, CreateApplicationCommands},
     client::Context,
     model::{
         id::GuildId,
         interactions::{
             application_command::{
-                ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+                ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+                ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
             },
             InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
         },
@@ -16,12 +17,146 @@ use serenity::{
 };
 
 use crate::utils::{
-    discord::{get_channel_of_member, join_channel, play_from_file, play_youtube},
-    error::{check_msg, handle_error},
+    discord::{get_channel_of_member, join_channel, play_from_file_with_options, play_url},
+    effects::{parse_factor, parse_position, AudioOptions, Effect, EFFECTS},
+    guild_settings::get_duration_limits,
+    history::{PlayInterface, PlayOrigin},
+    import::get_downloader,
+    sound_files::save_clip,
+    url_source::{classify_url, get_url_config, is_url},
 };
 
+mod combo;
+mod controls;
+mod favorites;
+mod follow;
+mod history;
+mod idle;
+mod limits;
+mod playlist;
+mod quiz;
+mod random;
+mod reaction;
+mod recording;
+mod say;
+mod schedule;
+mod sound;
+mod soundboard;
+mod stats;
+mod timer;
+mod trigger;
+mod yt;
+
 pub const PLAY_COMMAND: &str = "play";
 
+/// Registers all slash commands Pascal understands
+pub fn register_commands(
+    commands: &mut CreateApplicationCommands,
+) -> &mut CreateApplicationCommands {
+    commands
+        .create_application_command(|command| {
+            command
+                .name(PLAY_COMMAND)
+                .description("Command Pascal to play a sound")
+                .create_option(|option| {
+                    option
+                        .name("sound")
+                        .description("Name of the sound to play. Use the list command to see all possible values")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                        .set_autocomplete(true)
+                })
+                .create_option(|option| {
+                    effect_option(option, "effect", "Effect to apply to the sound")
+                })
+                .create_option(|option| {
+                    effect_option(option, "effect2", "Another effect to stack on top")
+                })
+                .create_option(|option| {
+                    option
+                        .name("pitch")
+                        .description("Pitch factor between 0.5 and 2, e.g. 1.5")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+                .create_option(|option| {
+                    option
+                        .name("speed")
+                        .description("Speed factor between 0.5 and 2, e.g. 0.8")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+                .create_option(|option| {
+                    option
+                        .name("start")
+                        .description("Position to start playing at, e.g. 1:23 or 4.5")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+                .create_option(|option| {
+                    option
+                        .name("end")
+                        .description("Position to stop playing at, e.g. 1:30 or 12")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+                .create_option(|option| {
+                    option
+                        .name("save_as")
+                        .description("Admins only: save the trimmed clip as a new sound with this name")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_application_command(|command| soundboard::create_command(command))
+        .create_application_command(|command| random::create_command(command))
+        .create_application_command(|command| history::create_history_command(command))
+        .create_application_command(|command| history::create_again_command(command))
+        .create_application_command(|command| history::create_who_command(command))
+        .create_application_command(|command| stats::create_command(command))
+        .create_application_command(|command| favorites::create_fav_command(command))
+        .create_application_command(|command| favorites::create_quick_slot_command(command))
+        .create_application_command(|command| combo::create_command(command))
+        .create_application_command(|command| playlist::create_command(command))
+        .create_application_command(|command| controls::create_loop_command(command))
+        .create_application_command(|command| controls::create_seek_command(command))
+        .create_application_command(|command| controls::create_restart_command(command))
+        .create_application_command(|command| controls::create_stop_command(command))
+        .create_application_command(|command| controls::create_skip_command(command))
+        .create_application_command(|command| sound::create_command(command))
+        .create_application_command(|command| yt::create_command(command))
+        .create_application_command(|command| limits::create_command(command))
+        .create_application_command(|command| idle::create_command(command))
+        .create_application_command(|command| follow::create_follow_command(command))
+        .create_application_command(|command| follow::create_unfollow_command(command))
+        .create_application_command(|command| trigger::create_command(command))
+        .create_application_command(|command| reaction::create_command(command))
+        .create_application_command(|command| schedule::create_command(command))
+        .create_application_command(|command| timer::create_command(command))
+        .create_application_command(|command| say::create_command(command))
+        .create_application_command(|command| quiz::create_command(command))
+        .create_application_command(|command| recording::create_recording_command(command))
+        .create_application_command(|command| recording::create_clip_command(command))
+}
+
+fn effect_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    name: &str,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    option
+        .name(name)
+        .description(description)
+        .kind(ApplicationCommandOptionType::String)
+        .required(false);
+
+    for effect in EFFECTS.iter() {
+        option.add_string_choice(effect.name(), effect.name());
+    }
+
+    option
+}
+
 pub async fn handle_slash_commands(ctx: Context, command: ApplicationCommandInteraction) {
     let guild_id = match command.guild_id {
         Some(gid) => gid,
@@ -34,9 +169,208 @@ pub async fn handle_slash_commands(ctx: Context, command: ApplicationCommandInte
         }
     };
 
-    if command.data.name.as_str() == PLAY_COMMAND {
-        handle_play_command(ctx, command, guild_id).await
+    match command.data.name.as_str() {
+        PLAY_COMMAND => handle_play_command(ctx, command, guild_id).await,
+        soundboard::SOUNDBOARD_COMMAND => {
+            soundboard::handle_soundboard_command(ctx, command, guild_id).await
+        }
+        random::RANDOM_COMMAND => random::handle_random_command(ctx, command, guild_id).await,
+        history::HISTORY_COMMAND => history::handle_history_command(ctx, command, guild_id).await,
+        history::AGAIN_COMMAND => history::handle_again_command(ctx, command, guild_id).await,
+        history::WHO_COMMAND => history::handle_who_command(ctx, command, guild_id).await,
+        stats::STATS_COMMAND => stats::handle_stats_command(ctx, command, guild_id).await,
+        combo::COMBO_COMMAND => combo::handle_combo_command(ctx, command, guild_id).await,
+        playlist::PLAYLIST_COMMAND => {
+            playlist::handle_playlist_command(ctx, command, guild_id).await
+        }
+        controls::LOOP_COMMAND => controls::handle_loop_command(ctx, command, guild_id).await,
+        controls::SEEK_COMMAND => controls::handle_seek_command(ctx, command, guild_id).await,
+        controls::RESTART_COMMAND => controls::handle_restart_command(ctx, command, guild_id).await,
+        controls::STOP_COMMAND => controls::handle_stop_command(ctx, command, guild_id).await,
+        controls::SKIP_COMMAND => controls::handle_skip_command(ctx, command, guild_id).await,
+        sound::SOUND_COMMAND => sound::handle_sound_command(ctx, command, guild_id).await,
+        yt::YT_COMMAND => yt::handle_yt_command(ctx, command).await,
+        limits::LIMITS_COMMAND => limits::handle_limits_command(ctx, command, guild_id).await,
+        idle::IDLE_COMMAND => idle::handle_idle_command(ctx, command, guild_id).await,
+        follow::FOLLOW_COMMAND => follow::handle_follow_command(ctx, command, guild_id).await,
+        follow::UNFOLLOW_COMMAND => follow::handle_unfollow_command(ctx, command, guild_id).await,
+        trigger::TRIGGER_COMMAND => trigger::handle_trigger_command(ctx, command, guild_id).await,
+        reaction::REACTION_COMMAND => {
+            reaction::handle_reaction_command(ctx, command, guild_id).await
+        }
+        schedule::SCHEDULE_COMMAND => {
+            schedule::handle_schedule_command(ctx, command, guild_id).await
+        }
+        timer::TIMER_COMMAND => timer::handle_timer_command(ctx, command, guild_id).await,
+        say::SAY_COMMAND => say::handle_say_command(ctx, command, guild_id).await,
+        quiz::QUIZ_COMMAND => quiz::handle_quiz_command(ctx, command, guild_id).await,
+        recording::RECORDING_COMMAND => {
+            recording::handle_recording_command(ctx, command, guild_id).await
+        }
+        recording::CLIP_COMMAND => recording::handle_clip_command(ctx, command, guild_id).await,
+        favorites::FAV_COMMAND => favorites::handle_fav_command(ctx, command).await,
+        favorites::QUICK_SLOT_COMMAND => {
+            favorites::handle_quick_slot_command(ctx, command, guild_id).await
+        }
+        _ => (),
+    };
+}
+
+/// Responds to a slash command with a message only the caller can see
+pub(crate) async fn respond<D: fmt::Display>(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    content: D,
+) {
+    send_response(ctx, command, content, true).await;
+}
+
+/// Responds to a slash command with a message visible to the whole channel
+pub(crate) async fn announce<D: fmt::Display>(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    content: D,
+) {
+    send_response(ctx, command, content, false).await;
+}
+
+async fn send_response<D: fmt::Display>(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    content: D,
+    ephemeral: bool,
+) {
+    let flags = if ephemeral {
+        InteractionApplicationCommandCallbackDataFlags::EPHEMERAL
+    } else {
+        InteractionApplicationCommandCallbackDataFlags::empty()
+    };
+    if let Err(e) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| message.content(content).flags(flags))
+        })
+        .await
+    {
+        error!("Error responding to slash command: {}", e);
+    }
+}
+
+/// Acknowledges a slash command that takes a while, showing a thinking indicator.
+/// The answer is given with `edit_response` afterwards.
+pub(crate) async fn defer(ctx: &Context, command: &ApplicationCommandInteraction, ephemeral: bool) {
+    let flags = if ephemeral {
+        InteractionApplicationCommandCallbackDataFlags::EPHEMERAL
+    } else {
+        InteractionApplicationCommandCallbackDataFlags::empty()
     };
+    if let Err(e) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|message| message.flags(flags))
+        })
+        .await
+    {
+        error!("Error deferring slash command: {}", e);
+    }
+}
+
+/// Replaces the response to a deferred slash command
+pub(crate) async fn edit_response<D: fmt::Display>(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    content: D,
+) {
+    if let Err(e) = command
+        .edit_original_interaction_response(&ctx.http, |response| response.content(content))
+        .await
+    {
+        error!("Error editing slash command response: {}", e);
+    }
+}
+
+/// Looks up the resolved value of the option with the given name
+pub(crate) fn get_option<'a>(
+    options: &'a [ApplicationCommandInteractionDataOption],
+    name: &str,
+) -> Option<&'a ApplicationCommandInteractionDataOptionValue> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.resolved.as_ref())
+}
+
+/// Looks up the value of the string option with the given name
+pub(crate) fn get_string_option<'a>(
+    options: &'a [ApplicationCommandInteractionDataOption],
+    name: &str,
+) -> Option<&'a str> {
+    match get_option(options, name) {
+        Some(ApplicationCommandInteractionDataOptionValue::String(value)) => Some(value.as_str()),
+        _ => None,
+    }
+}
+
+/// Returns true if the boolean option with the given name is present and set
+pub(crate) fn get_bool_option(
+    options: &[ApplicationCommandInteractionDataOption],
+    name: &str,
+) -> bool {
+    matches!(
+        get_option(options, name),
+        Some(ApplicationCommandInteractionDataOptionValue::Boolean(true))
+    )
+}
+
+/// Returns true if the caller may manage the guild's Pascal setup
+pub(crate) fn is_admin(command: &ApplicationCommandInteraction) -> bool {
+    command
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .map_or(false, |permissions| {
+            permissions.administrator() || permissions.manage_guild()
+        })
+}
+
+/// Collects the effect options of the play command
+fn get_audio_options(
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<AudioOptions, String> {
+    let mut audio_options = AudioOptions::default();
+
+    for name in ["effect", "effect2"] {
+        if let Some(effect) = get_string_option(options, name) {
+            audio_options.add_effect(effect.parse::<Effect>()?);
+        }
+    }
+    if let Some(pitch) = get_string_option(options, "pitch") {
+        audio_options.pitch = Some(parse_factor("pitch", Some(pitch))?);
+    }
+    if let Some(speed) = get_string_option(options, "speed") {
+        audio_options.speed = Some(parse_factor("speed", Some(speed))?);
+    }
+
+    apply_trim_options(options, &mut audio_options)?;
+
+    Ok(audio_options)
+}
+
+/// Reads the `start` and `end` options into the audio options
+pub(crate) fn apply_trim_options(
+    options: &[ApplicationCommandInteractionDataOption],
+    audio_options: &mut AudioOptions,
+) -> Result<(), String> {
+    let start = get_string_option(options, "start")
+        .map(|start| parse_position("start", Some(start)))
+        .transpose()?;
+    let end = get_string_option(options, "end")
+        .map(|end| parse_position("end", Some(end)))
+        .transpose()?;
+
+    audio_options.set_trim(start, end)
 }
 
 async fn handle_play_command(
@@ -44,52 +378,153 @@ async fn handle_play_command(
     command: ApplicationCommandInteraction,
     guild_id: GuildId,
 ) {
+    let audio_options = match get_audio_options(&command.data.options) {
+        Ok(audio_options) => audio_options,
+        Err(err) => {
+            respond(&ctx, &command, err).await;
+            return;
+        }
+    };
+    let sound_name = match get_string_option(&command.data.options, "sound") {
+        Some(sound_name) => sound_name,
+        None => {
+            respond(&ctx, &command, "Invalid sound name input").await;
+            return;
+        }
+    };
+    let clip_name = get_string_option(&command.data.options, "save_as");
+    // The library is shared by every server Pascal is in
+    if clip_name.is_some() && !is_admin(&command) {
+        respond(&ctx, &command, "Only admins can save clips.").await;
+        return;
+    }
+
+    // Probing URLs and rendering clips takes longer than Discord waits for an answer.
+    // Saved clips are announced to everyone, playing is only confirmed to the caller.
+    defer(&ctx, &command, clip_name.is_none()).await;
+
     if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
         if let Err(e) = join_channel(&ctx, guild_id, channel_id).await {
             error!("Failed to join channel: {}", e);
         }
     }
 
-    // Only use the first provided argument
-    let option = command
-        .data
-        .options
-        .get(0)
-        .expect("Expected name of sound to play")
-        .resolved
-        .as_ref()
-        .expect("Expected resolved command option");
-
-    if let ApplicationCommandInteractionDataOptionValue::String(sound_name) = option {
-        if sound_name.starts_with("https://") {
-            play_youtube(&ctx, command.channel_id, guild_id, sound_name)
-                .await
-                .with_context(|| handle_error("Failed to play from youtube".to_string()))
-                .unwrap();
-        } else {
-            play_from_file(&ctx, command.channel_id, guild_id, sound_name)
-                .await
-                .with_context(|| handle_error("Failed to play sound".to_string()))
-                .unwrap();
-        }
-    } else {
-        check_msg(
-            command
-                .channel_id
-                .say(&ctx.http, "Invalid sound name input")
-                .await,
-        );
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    if let Some(clip_name) = clip_name {
+        save_and_play_clip(
+            &ctx,
+            &command,
+            guild_id,
+            sound_name,
+            clip_name,
+            &audio_options,
+            origin,
+        )
+        .await;
+        return;
     }
 
-    let flags = InteractionApplicationCommandCallbackDataFlags::EPHEMERAL;
-    if let Err(e) = command
-        .create_interaction_response(&ctx.http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| message.content("Tight.").flags(flags))
-        })
+    let result = if is_url(sound_name) {
+        play_url(
+            &ctx,
+            command.channel_id,
+            guild_id,
+            sound_name,
+            origin,
+            &audio_options,
+        )
+        .await
+    } else {
+        play_from_file_with_options(
+            &ctx,
+            command.channel_id,
+            guild_id,
+            sound_name,
+            origin,
+            &audio_options,
+        )
         .await
+    };
+
+    match result {
+        Ok(()) => edit_response(&ctx, &command, "Tight.").await,
+        Err(e) => {
+            error!("Failed to play {}: {}", sound_name, e);
+            edit_response(&ctx, &command, "Could not play that.").await;
+        }
+    }
+}
+
+/// Saves the sound or URL with the options applied as a new sound and plays it.
+/// The command has to be deferred already.
+async fn save_and_play_clip(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    source: &str,
+    clip_name: &str,
+    audio_options: &AudioOptions,
+    origin: PlayOrigin,
+) {
+    // URLs go through the same checks as when they are played
+    if is_url(source) {
+        match classify_url(ctx, source, Some(guild_id)).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(reason)) => {
+                edit_response(ctx, command, reason).await;
+                return;
+            }
+            Err(e) => {
+                error!("Failed to check URL {}: {}", source, e);
+                edit_response(ctx, command, "Could not save the clip.").await;
+                return;
+            }
+        }
+    }
+
+    let (limit, downloader, url_config) = match (
+        get_duration_limits(ctx, guild_id).await,
+        get_downloader(ctx).await,
+        get_url_config(ctx).await,
+    ) {
+        (Ok(limits), Ok(downloader), Ok(url_config)) => (limits.sound, downloader, url_config),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            error!("Error preparing to save clip: {}", e);
+            edit_response(ctx, command, "Could not save the clip.").await;
+            return;
+        }
+    };
+
+    match save_clip(
+        source,
+        clip_name,
+        audio_options,
+        limit,
+        &downloader,
+        url_config.allow_private_hosts,
+    )
+    .await
     {
-        error!("Error responding to slash command: {}", e);
+        Ok(Ok(())) => {
+            // The saved clip already has everything applied, so it's played as is
+            if let Err(e) = play_from_file_with_options(
+                ctx,
+                command.channel_id,
+                guild_id,
+                clip_name,
+                origin,
+                &AudioOptions::default(),
+            )
+            .await
+            {
+                error!("Failed to play saved clip {}: {}", clip_name, e);
+            }
+            edit_response(ctx, command, format!("✂️ Saved **{}**", clip_name)).await;
+        }
+        Ok(Err(reason)) => edit_response(ctx, command, reason).await,
+        Err(e) => {
+            error!("Failed to save clip {}: {}", clip_name, e);
+            edit_response(ctx, command, "Could not save the clip.").await;
+        }
     }
 }
@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption},
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    combos::{delete_combo, get_combos, save_combo},
+    discord::{get_channel_of_member, join_channel},
+    effects::AudioOptions,
+    history::{PlayInterface, PlayOrigin},
+    sequence::{parse_steps, play_sequence, SequenceStep, MAX_PAUSE},
+    sound_files::{get_sound_files, validate_sound_name},
+};
+
+use super::{get_option, get_string_option, is_admin, respond};
+
+pub const COMBO_COMMAND: &str = "combo";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(COMBO_COMMAND)
+        .description("Play several sounds back to back")
+        .create_option(|option| {
+            option
+                .name("play")
+                .description("Play sounds back to back")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| sounds_option(sub_option))
+                .create_sub_option(|sub_option| gap_option(sub_option))
+        })
+        .create_option(|option| {
+            option
+                .name("save")
+                .description("Save a combo that can be played like any other sound")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Name to play the combo by")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| sounds_option(sub_option))
+                .create_sub_option(|sub_option| gap_option(sub_option))
+        })
+        .create_option(|option| {
+            option
+                .name("delete")
+                .description("Delete a saved combo")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Name of the combo")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List the saved combos")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+fn sounds_option(
+    option: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    option
+        .name("sounds")
+        .description("Comma separated sounds, pauses like 500ms or 1.5s are allowed in between")
+        .kind(ApplicationCommandOptionType::String)
+        .required(true)
+}
+
+fn gap_option(option: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    option
+        .name("gap")
+        .description("Milliseconds of silence between two sounds")
+        .kind(ApplicationCommandOptionType::Integer)
+        .required(false)
+}
+
+fn get_gap(options: &[ApplicationCommandInteractionDataOption]) -> Duration {
+    match get_option(options, "gap") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(gap)) if *gap > 0 => {
+            Duration::from_millis(*gap as u64).min(MAX_PAUSE)
+        }
+        _ => Duration::ZERO,
+    }
+}
+
+fn split_items(sounds: &str) -> Vec<String> {
+    sounds
+        .split(',')
+        .map(|item| item.trim().to_owned())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+pub async fn handle_combo_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+
+    let result = match subcommand.name.as_str() {
+        "play" => {
+            let items = split_items(get_string_option(options, "sounds").unwrap_or_default());
+            play_combo(&ctx, &command, guild_id, items, get_gap(options)).await
+        }
+        "save" => match get_string_option(options, "name") {
+            Some(name) => {
+                let items = split_items(get_string_option(options, "sounds").unwrap_or_default());
+                save_report(&ctx, &command, guild_id, name, items, get_gap(options)).await
+            }
+            None => Ok("Please name the combo.".to_string()),
+        },
+        "delete" => match get_string_option(options, "name") {
+            Some(name) => delete_combo(&ctx, guild_id, name, command.user.id, is_admin(&command))
+                .await
+                .map(|deleted| match deleted {
+                    Ok(true) => format!("Deleted combo **{}**.", name),
+                    Ok(false) => format!("There is no combo called **{}**.", name),
+                    Err(reason) => reason,
+                }),
+            None => Ok("Please name the combo.".to_string()),
+        },
+        "list" => list_report(&ctx, guild_id).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error handling combo command: {}", e);
+            respond(&ctx, &command, "Something went wrong with that combo.").await;
+        }
+    }
+}
+
+async fn play_combo(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    items: Vec<String>,
+    gap: Duration,
+) -> Result<String> {
+    let steps = match parse_steps(&items) {
+        Ok(steps) => steps,
+        Err(reason) => return Ok(reason),
+    };
+    if steps.is_empty() {
+        return Ok("Please name at least one sound.".to_string());
+    }
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        if let Err(e) = join_channel(ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    play_sequence(
+        ctx,
+        command.channel_id,
+        guild_id,
+        steps,
+        gap,
+        origin,
+        AudioOptions::default(),
+    )
+    .await?;
+
+    Ok("Tight.".to_string())
+}
+
+async fn save_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    name: &str,
+    items: Vec<String>,
+    gap: Duration,
+) -> Result<String> {
+    // Combos are played by name just like sounds
+    if let Err(reason) = validate_sound_name(name) {
+        return Ok(reason);
+    }
+    let sound_files = get_sound_files()?;
+    if sound_files.contains_key(name) {
+        return Ok(format!("There already is a sound called **{}**.", name));
+    }
+
+    let steps = match parse_steps(&items) {
+        Ok(steps) => steps,
+        Err(reason) => return Ok(reason),
+    };
+    let unknown: Vec<&str> = steps
+        .iter()
+        .filter_map(|step| match step {
+            SequenceStep::Sound(sound) if !sound_files.contains_key(sound) => Some(sound.as_str()),
+            _ => None,
+        })
+        .collect();
+    if !unknown.is_empty() {
+        return Ok(format!(
+            "I don't know these sounds: **{}**",
+            unknown.join("**, **")
+        ));
+    }
+    if !steps
+        .iter()
+        .any(|step| matches!(step, SequenceStep::Sound(_)))
+    {
+        return Ok("Please name at least one sound.".to_string());
+    }
+
+    let saved = save_combo(
+        ctx,
+        guild_id,
+        name,
+        items,
+        gap,
+        command.user.id,
+        is_admin(command),
+    )
+    .await?;
+    if let Err(reason) = saved {
+        return Ok(reason);
+    }
+
+    Ok(format!(
+        "Saved combo **{}**. Play it like any other sound.",
+        name
+    ))
+}
+
+async fn list_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let combos = get_combos(ctx, guild_id).await?;
+    if combos.is_empty() {
+        return Ok("There are no saved combos yet.".to_string());
+    }
+
+    let mut output = String::from("Saved combos:\n");
+    for (name, combo) in combos {
+        output.push_str(&format!("\t- **{}**: {}", name, combo.items.join(", ")));
+        if combo.gap_ms > 0 {
+            output.push_str(&format!(" ({}ms gap)", combo.gap_ms));
+        }
+        output.push('\n');
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandOptionType,
+        },
+    },
+};
+use songbird::tracks::TrackHandle;
+
+use crate::utils::{
+    discord::{skip_track, stop_playback},
+    tracks::{format_timestamp, get_current_track, parse_timestamp},
+};
+
+use super::{get_string_option, respond};
+
+pub const LOOP_COMMAND: &str = "loop";
+pub const SEEK_COMMAND: &str = "seek";
+pub const RESTART_COMMAND: &str = "restart";
+pub const STOP_COMMAND: &str = "stop";
+pub const SKIP_COMMAND: &str = "skip";
+
+pub fn create_loop_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(LOOP_COMMAND)
+        .description("Loop the current track")
+        .create_option(|option| {
+            option
+                .name("times")
+                .description("How often to repeat it, or off. Loops forever if left out")
+                .kind(ApplicationCommandOptionType::String)
+                .required(false)
+        })
+}
+
+pub fn create_seek_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(SEEK_COMMAND)
+        .description("Jump to a position in the current track")
+        .create_option(|option| {
+            option
+                .name("position")
+                .description("Position like 1:23 or 83")
+                .kind(ApplicationCommandOptionType::String)
+                .required(true)
+        })
+}
+
+pub fn create_restart_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(RESTART_COMMAND)
+        .description("Play the current track from the start")
+}
+
+pub fn create_stop_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(STOP_COMMAND)
+        .description("Fade out everything that's playing and clear the queue")
+}
+
+pub fn create_skip_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(SKIP_COMMAND)
+        .description("Fade out the current track and play the next one in the queue")
+}
+
+/// Fetches the guild's current track, telling the caller if there is none
+async fn current_track_or_respond(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+) -> Option<TrackHandle> {
+    match get_current_track(ctx, guild_id).await {
+        Ok(Some(track)) => Some(track),
+        Ok(None) => {
+            respond(ctx, command, "Nothing is playing right now.").await;
+            None
+        }
+        Err(e) => {
+            error!("Error fetching current track: {}", e);
+            respond(
+                ctx,
+                command,
+                "Something went wrong fetching the current track.",
+            )
+            .await;
+            None
+        }
+    }
+}
+
+pub async fn handle_loop_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let times = get_string_option(&command.data.options, "times").map(str::trim);
+    let track = match current_track_or_respond(&ctx, &command, guild_id).await {
+        Some(track) => track,
+        None => return,
+    };
+
+    let (result, message) = match times {
+        None => (
+            track.enable_loop(),
+            "Looping the current track.".to_string(),
+        ),
+        Some(times) if times.eq_ignore_ascii_case("off") => {
+            (track.disable_loop(), "Stopped looping.".to_string())
+        }
+        Some(times) => match times.parse::<usize>() {
+            Ok(times) => (
+                track.loop_for(times),
+                format!("Repeating the current track {} more times.", times),
+            ),
+            Err(_) => {
+                respond(&ctx, &command, "Please give a number of repeats or off.").await;
+                return;
+            }
+        },
+    };
+
+    match result {
+        Ok(_) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error changing loop state: {}", e);
+            respond(&ctx, &command, "This track can't be looped.").await;
+        }
+    }
+}
+
+pub async fn handle_seek_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let position =
+        match get_string_option(&command.data.options, "position").and_then(parse_timestamp) {
+            Some(position) => position,
+            None => {
+                respond(&ctx, &command, "Please give a position like 1:23.").await;
+                return;
+            }
+        };
+
+    seek(&ctx, &command, guild_id, position).await;
+}
+
+pub async fn handle_restart_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    seek(&ctx, &command, guild_id, Duration::ZERO).await;
+}
+
+async fn seek(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    position: Duration,
+) {
+    let track = match current_track_or_respond(ctx, command, guild_id).await {
+        Some(track) => track,
+        None => return,
+    };
+
+    if !track.is_seekable() {
+        respond(ctx, command, "This track can't be seeked.").await;
+        return;
+    }
+
+    if let Some(duration) = track.metadata().duration {
+        if position > duration {
+            respond(
+                ctx,
+                command,
+                format!("The track is only {} long.", format_timestamp(duration)),
+            )
+            .await;
+            return;
+        }
+    }
+
+    match track.seek_time(position) {
+        Ok(_) => {
+            respond(
+                ctx,
+                command,
+                format!("Jumped to {}.", format_timestamp(position)),
+            )
+            .await
+        }
+        Err(e) => {
+            error!("Error seeking track: {}", e);
+            respond(ctx, command, "Something went wrong seeking the track.").await;
+        }
+    }
+}
+
+pub async fn handle_stop_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    // Respond first, fading out takes longer than Discord waits for an answer
+    respond(&ctx, &command, "Stopping.").await;
+
+    if let Err(e) = stop_playback(&ctx, guild_id).await {
+        error!("Error stopping playback: {}", e);
+    }
+}
+
+pub async fn handle_skip_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    match skip_track(&ctx, guild_id).await {
+        Ok(true) => respond(&ctx, &command, "Skipped.").await,
+        Ok(false) => respond(&ctx, &command, "Nothing is playing right now.").await,
+        Err(e) => {
+            error!("Error skipping track: {}", e);
+            respond(&ctx, &command, "Could not skip the current track.").await;
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::{GuildId, UserId},
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel, play_from_file},
+    favorites::{
+        add_favorite, get_favorites, get_quick_slot, remove_favorite, AddFavoriteResult,
+        MAX_FAVORITES,
+    },
+    history::{PlayInterface, PlayOrigin},
+    sound_files::get_sound_files,
+};
+
+use super::{get_option, respond};
+
+pub const FAV_COMMAND: &str = "fav";
+pub const QUICK_SLOT_COMMAND: &str = "q";
+
+pub fn create_fav_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(FAV_COMMAND)
+        .description("Manage your favorite sounds and quick slots")
+        .create_option(|option| {
+            option
+                .name("add")
+                .description("Add a sound to your favorites, filling the next quick slot")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Name of the sound")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Remove a sound from your favorites")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Name of the sound")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List your favorites and their quick slots")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub fn create_quick_slot_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(QUICK_SLOT_COMMAND)
+        .description("Play the sound in one of your quick slots")
+        .create_option(|option| {
+            option
+                .name("slot")
+                .description("Number of the quick slot, see /fav list")
+                .kind(ApplicationCommandOptionType::Integer)
+                .required(true)
+        })
+}
+
+pub async fn handle_fav_command(ctx: Context, command: ApplicationCommandInteraction) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let sound = match get_option(&subcommand.options, "sound") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(sound)) => Some(sound.as_str()),
+        _ => None,
+    };
+
+    let result = match (subcommand.name.as_str(), sound) {
+        ("add", Some(sound)) => add_report(&ctx, command.user.id, sound).await,
+        ("remove", Some(sound)) => {
+            remove_favorite(&ctx, command.user.id, sound)
+                .await
+                .map(|removed| {
+                    if removed {
+                        format!("Removed **{}** from your favorites.", sound)
+                    } else {
+                        format!("**{}** is not one of your favorites.", sound)
+                    }
+                })
+        }
+        ("list", _) => list_report(&ctx, command.user.id).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error handling favorites command: {}", e);
+            respond(
+                &ctx,
+                &command,
+                "Something went wrong managing your favorites.",
+            )
+            .await;
+        }
+    }
+}
+
+async fn add_report(ctx: &Context, user_id: UserId, sound: &str) -> Result<String> {
+    if !get_sound_files()?.contains_key(sound) {
+        return Ok(format!("I don't know this sound: **{}**", sound));
+    }
+
+    Ok(match add_favorite(ctx, user_id, sound).await? {
+        AddFavoriteResult::Added(slot) => {
+            format!(
+                "Added **{}** to your favorites in quick slot {}.",
+                sound, slot
+            )
+        }
+        AddFavoriteResult::AlreadyFavorite(slot) => {
+            format!("**{}** is already in quick slot {}.", sound, slot)
+        }
+        AddFavoriteResult::Full => format!(
+            "You already have {} favorites, remove one first.",
+            MAX_FAVORITES
+        ),
+    })
+}
+
+async fn list_report(ctx: &Context, user_id: UserId) -> Result<String> {
+    let favorites = get_favorites(ctx, user_id).await?;
+    if favorites.is_empty() {
+        return Ok("You have no favorites yet. Add one with `/fav add`.".to_string());
+    }
+
+    let mut output = String::from("Your quick slots:\n");
+    for (index, favorite) in favorites.iter().enumerate() {
+        output.push_str(&format!("`{}` {}\n", index + 1, favorite));
+    }
+
+    Ok(output)
+}
+
+pub async fn handle_quick_slot_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let slot = match get_option(&command.data.options, "slot") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(slot)) if *slot > 0 => {
+            *slot as usize
+        }
+        _ => {
+            respond(&ctx, &command, "Please pick a quick slot.").await;
+            return;
+        }
+    };
+
+    let sound_name = match get_quick_slot(&ctx, command.user.id, slot).await {
+        Ok(Some(sound_name)) => sound_name,
+        Ok(None) => {
+            respond(
+                &ctx,
+                &command,
+                format!("Quick slot {} is empty. Use `/fav add` to fill it.", slot),
+            )
+            .await;
+            return;
+        }
+        Err(e) => {
+            error!("Error fetching quick slot: {}", e);
+            respond(
+                &ctx,
+                &command,
+                "Something went wrong fetching your quick slot.",
+            )
+            .await;
+            return;
+        }
+    };
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        if let Err(e) = join_channel(&ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    if let Err(e) = play_from_file(&ctx, command.channel_id, guild_id, &sound_name, origin).await {
+        error!("Failed to play quick slot: {}", e);
+    }
+
+    respond(&ctx, &command, format!("Playing **{}**", sound_name)).await;
+}
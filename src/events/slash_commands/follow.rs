@@ -0,0 +1,92 @@
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel},
+    follow::{follow_user, unfollow_user},
+};
+
+use super::{announce, get_option, respond};
+
+pub const FOLLOW_COMMAND: &str = "follow";
+pub const UNFOLLOW_COMMAND: &str = "unfollow";
+
+pub fn create_follow_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(FOLLOW_COMMAND)
+        .description("Make Pascal follow someone between voice channels")
+        .create_option(|option| {
+            option
+                .name("user")
+                .description("Who to follow, yourself if left out")
+                .kind(ApplicationCommandOptionType::User)
+                .required(false)
+        })
+}
+
+pub fn create_unfollow_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(UNFOLLOW_COMMAND)
+        .description("Stop following anyone between voice channels")
+}
+
+pub async fn handle_follow_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let user = match get_option(&command.data.options, "user") {
+        Some(ApplicationCommandInteractionDataOptionValue::User(user, _)) => user.clone(),
+        _ => command.user.clone(),
+    };
+    if user.bot {
+        respond(&ctx, &command, "Pascal only follows humans.").await;
+        return;
+    }
+
+    if let Err(e) = follow_user(&ctx, guild_id, user.id).await {
+        error!("Error following user: {}", e);
+        respond(&ctx, &command, "Could not follow them.").await;
+        return;
+    }
+
+    // Catch up right away instead of waiting for their next channel switch
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, user.id).await {
+        if let Err(e) = join_channel(&ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    announce(&ctx, &command, format!("👣 Following **{}**", user.name)).await;
+}
+
+pub async fn handle_unfollow_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    match unfollow_user(&ctx, guild_id).await {
+        Ok(Some(user_id)) => {
+            announce(&ctx, &command, format!("Stopped following <@{}>", user_id)).await
+        }
+        Ok(None) => respond(&ctx, &command, "Pascal isn't following anyone.").await,
+        Err(e) => {
+            error!("Error unfollowing user: {}", e);
+            respond(&ctx, &command, "Could not stop following.").await;
+        }
+    }
+}
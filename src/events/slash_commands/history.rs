@@ -0,0 +1,165 @@
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{id::GuildId, interactions::application_command::ApplicationCommandInteraction},
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel, play_from_file, play_url},
+    effects::AudioOptions,
+    history::{
+        get_currently_playing, get_history, HistoryEntry, PlayInterface, PlayOrigin, PlayedMedia,
+    },
+};
+
+use super::{defer, edit_response, respond};
+
+pub const HISTORY_COMMAND: &str = "history";
+pub const AGAIN_COMMAND: &str = "again";
+pub const WHO_COMMAND: &str = "who";
+
+/// Number of plays listed by the history command
+const HISTORY_PAGE_SIZE: usize = 10;
+
+pub fn create_history_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(HISTORY_COMMAND)
+        .description("Show the most recently played sounds")
+}
+
+pub fn create_again_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(AGAIN_COMMAND)
+        .description("Play the last played sound again")
+}
+
+pub fn create_who_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(WHO_COMMAND)
+        .description("Show who triggered the sound that is currently playing")
+}
+
+fn describe_entry(entry: &HistoryEntry) -> String {
+    format!(
+        "{} by <@{}> via {} <t:{}:R>",
+        entry.media,
+        entry.origin.user_id,
+        entry.origin.interface,
+        entry.unix_timestamp()
+    )
+}
+
+pub async fn handle_history_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let history = match get_history(&ctx, guild_id).await {
+        Ok(history) => history,
+        Err(e) => {
+            error!("Error fetching play history: {}", e);
+            respond(&ctx, &command, "Something went wrong fetching the history.").await;
+            return;
+        }
+    };
+
+    if history.is_empty() {
+        respond(&ctx, &command, "Nothing has been played yet.").await;
+        return;
+    }
+
+    let mut output = String::from("Recently played:\n");
+    for (index, entry) in history.iter().take(HISTORY_PAGE_SIZE).enumerate() {
+        output.push_str(&format!("`{}.` {}\n", index + 1, describe_entry(entry)));
+    }
+
+    respond(&ctx, &command, output).await;
+}
+
+pub async fn handle_again_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let last_media = match get_history(&ctx, guild_id).await {
+        Ok(history) => match history.into_iter().next() {
+            Some(entry) => entry.media,
+            None => {
+                respond(&ctx, &command, "Nothing has been played yet.").await;
+                return;
+            }
+        },
+        Err(e) => {
+            error!("Error fetching play history: {}", e);
+            respond(&ctx, &command, "Something went wrong fetching the history.").await;
+            return;
+        }
+    };
+
+    // Probing a URL again takes longer than Discord waits for an answer
+    defer(&ctx, &command, false).await;
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        if let Err(e) = join_channel(&ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    let result = match &last_media {
+        PlayedMedia::Sound(name) => {
+            play_from_file(&ctx, command.channel_id, guild_id, name, origin).await
+        }
+        PlayedMedia::Url(url) => {
+            play_url(
+                &ctx,
+                command.channel_id,
+                guild_id,
+                url,
+                origin,
+                &AudioOptions::default(),
+            )
+            .await
+        }
+    };
+
+    if let Err(e) = result {
+        error!("Failed to replay {}: {}", last_media, e);
+        edit_response(&ctx, &command, "Could not play that again.").await;
+        return;
+    }
+
+    edit_response(&ctx, &command, format!("🔁 Playing {} again", last_media)).await;
+}
+
+pub async fn handle_who_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let playing = match get_currently_playing(&ctx, guild_id).await {
+        Ok(playing) => playing,
+        Err(e) => {
+            error!("Error fetching play history: {}", e);
+            respond(&ctx, &command, "Something went wrong fetching the history.").await;
+            return;
+        }
+    };
+
+    if playing.is_empty() {
+        respond(&ctx, &command, "Nothing is playing right now.").await;
+        return;
+    }
+
+    let mut output = String::from("Currently playing:\n");
+    for entry in playing {
+        output.push_str(&format!("{}\n", describe_entry(&entry)));
+    }
+
+    respond(&ctx, &command, output).await;
+}
@@ -0,0 +1,70 @@
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::guild_settings::{get_idle_timeout, update_guild_settings};
+
+use super::{get_option, is_admin, respond};
+
+pub const IDLE_COMMAND: &str = "idle";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(IDLE_COMMAND)
+        .description("Show or change how long Pascal stays in a channel without playing anything")
+        .create_option(|option| {
+            option
+                .name("minutes")
+                .description("Minutes until Pascal leaves, 0 to never leave")
+                .kind(ApplicationCommandOptionType::Integer)
+                .required(false)
+        })
+}
+
+pub async fn handle_idle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    if let Some(ApplicationCommandInteractionDataOptionValue::Integer(minutes)) =
+        get_option(&command.data.options, "minutes")
+    {
+        if !is_admin(&command) {
+            respond(&ctx, &command, "Only admins can change the idle timeout.").await;
+            return;
+        }
+
+        let minutes = (*minutes).max(0) as u64;
+        if let Err(e) = update_guild_settings(&ctx, guild_id, |settings| {
+            settings.idle_timeout_mins = Some(minutes)
+        })
+        .await
+        {
+            error!("Error saving idle timeout: {}", e);
+            respond(&ctx, &command, "Could not change the idle timeout.").await;
+            return;
+        }
+    }
+
+    let message = match get_idle_timeout(&ctx, guild_id).await {
+        Ok(Some(timeout)) => format!(
+            "Pascal leaves after **{} minutes** without playing anything.",
+            timeout.as_secs() / 60
+        ),
+        Ok(None) => "Pascal stays in voice channels until everyone has left.".to_string(),
+        Err(e) => {
+            error!("Error fetching idle timeout: {}", e);
+            "Could not fetch the idle timeout.".to_string()
+        }
+    };
+    respond(&ctx, &command, message).await;
+}
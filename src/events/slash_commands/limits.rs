@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    guild_settings::{get_duration_limits, update_guild_settings},
+    tracks::format_timestamp,
+};
+
+use super::{get_option, is_admin, respond};
+
+pub const LIMITS_COMMAND: &str = "limits";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(LIMITS_COMMAND)
+        .description("Limit how long sounds and URLs may play on this server")
+        .create_option(|option| {
+            option
+                .name("show")
+                .description("Show the current limits")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("set")
+                .description("Change the limits, 0 removes a limit")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Seconds a library sound may play")
+                        .kind(ApplicationCommandOptionType::Integer)
+                        .required(false)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("url")
+                        .description("Seconds a URL may play")
+                        .kind(ApplicationCommandOptionType::Integer)
+                        .required(false)
+                })
+        })
+}
+
+pub async fn handle_limits_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+
+    let result = match subcommand.name.as_str() {
+        "show" => limits_report(&ctx, guild_id).await,
+        "set" if !is_admin(&command) => Ok("Only admins can change the limits.".to_string()),
+        "set" => set_limits(&ctx, guild_id, &subcommand.options).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(report) => respond(&ctx, &command, report).await,
+        Err(e) => {
+            error!("Error handling limits command: {}", e);
+            respond(&ctx, &command, "Something went wrong with the limits.").await;
+        }
+    }
+}
+
+fn get_seconds(options: &[ApplicationCommandInteractionDataOption], name: &str) -> Option<u64> {
+    match get_option(options, name) {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(secs)) => {
+            Some((*secs).max(0) as u64)
+        }
+        _ => None,
+    }
+}
+
+async fn set_limits(
+    ctx: &Context,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String> {
+    let sound = get_seconds(options, "sound");
+    let url = get_seconds(options, "url");
+    if sound.is_none() && url.is_none() {
+        return Ok("Please give me a limit for sounds or URLs.".to_string());
+    }
+
+    update_guild_settings(ctx, guild_id, |settings| {
+        if sound.is_some() {
+            settings.max_sound_duration_secs = sound;
+        }
+        if url.is_some() {
+            settings.max_url_duration_secs = url;
+        }
+    })
+    .await?;
+
+    limits_report(ctx, guild_id).await
+}
+
+async fn limits_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let limits = get_duration_limits(ctx, guild_id).await?;
+    let describe = |limit: Option<Duration>| match limit {
+        Some(limit) => format_timestamp(limit),
+        None => "no limit".to_string(),
+    };
+
+    Ok(format!(
+        "Sounds may play for **{}**, URLs for **{}**.",
+        describe(limits.sound),
+        describe(limits.url)
+    ))
+}
@@ -0,0 +1,286 @@
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption},
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel},
+    history::{PlayInterface, PlayOrigin},
+    playlists::{
+        add_to_playlist, create_playlist, delete_playlist, get_playlist, get_playlist_names,
+        play_playlist, remove_from_playlist, PlaylistError, PlaylistScope, MAX_PLAYLIST_LENGTH,
+    },
+};
+
+use super::{get_bool_option, get_string_option, is_admin, respond};
+
+pub const PLAYLIST_COMMAND: &str = "playlist";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(PLAYLIST_COMMAND)
+        .description("Manage and play playlists of sounds and URLs")
+        .create_option(|option| {
+            option
+                .name("create")
+                .description("Create a new playlist")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| name_option(sub_option))
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("shared")
+                        .description("Share the playlist with the whole server")
+                        .kind(ApplicationCommandOptionType::Boolean)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("add")
+                .description("Add a sound or URL to a playlist")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| name_option(sub_option))
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("item")
+                        .description("Name of a sound or a URL")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Remove an item from a playlist")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| name_option(sub_option))
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("item")
+                        .description("Position, sound name or URL of the item")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("play")
+                .description("Queue a playlist")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| name_option(sub_option))
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("shuffle")
+                        .description("Play the items in random order")
+                        .kind(ApplicationCommandOptionType::Boolean)
+                        .required(false)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("loop")
+                        .description("Start over when the playlist is done")
+                        .kind(ApplicationCommandOptionType::Boolean)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List your and this server's playlists, or the items of one")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Playlist to show the items of")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("delete")
+                .description("Delete a playlist")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| name_option(sub_option))
+        })
+}
+
+fn name_option(option: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    option
+        .name("name")
+        .description("Name of the playlist")
+        .kind(ApplicationCommandOptionType::String)
+        .required(true)
+}
+
+fn describe_error(error: PlaylistError, name: &str) -> String {
+    match error {
+        PlaylistError::NotFound => format!("There is no playlist called **{}**.", name),
+        PlaylistError::AlreadyExists => format!("There already is a playlist called **{}**.", name),
+        PlaylistError::UnknownItem => {
+            "That's neither a known sound nor a URL in this playlist.".to_string()
+        }
+        PlaylistError::Full => format!("Playlists can hold at most {} items.", MAX_PLAYLIST_LENGTH),
+        PlaylistError::NotOwner => {
+            "Only the owner of a shared playlist or an admin can change it.".to_string()
+        }
+    }
+}
+
+pub async fn handle_playlist_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+    let user_id = command.user.id;
+
+    let name = match get_string_option(options, "name") {
+        Some(name) => name,
+        None if subcommand.name == "list" => {
+            let result = list_playlists_report(&ctx, guild_id, &command).await;
+            return send_result(&ctx, &command, result).await;
+        }
+        None => return,
+    };
+    let item = get_string_option(options, "item").unwrap_or_default();
+
+    let result = match subcommand.name.as_str() {
+        "create" => {
+            let scope = if get_bool_option(options, "shared") {
+                PlaylistScope::Shared
+            } else {
+                PlaylistScope::Personal
+            };
+            create_playlist(&ctx, guild_id, user_id, name, scope)
+                .await
+                .map(|created| match created {
+                    Ok(_) => format!("Created playlist **{}**.", name),
+                    Err(e) => describe_error(e, name),
+                })
+        }
+        "add" => add_to_playlist(&ctx, guild_id, user_id, name, item, is_admin(&command))
+            .await
+            .map(|added| match added {
+                Ok(length) => format!("Added {} to **{}** at position {}.", item, name, length),
+                Err(e) => describe_error(e, name),
+            }),
+        "remove" => remove_from_playlist(&ctx, guild_id, user_id, name, item, is_admin(&command))
+            .await
+            .map(|removed| match removed {
+                Ok(removed) => format!("Removed {} from **{}**.", removed, name),
+                Err(e) => describe_error(e, name),
+            }),
+        "delete" => delete_playlist(&ctx, guild_id, user_id, name, is_admin(&command))
+            .await
+            .map(|deleted| match deleted {
+                Ok(_) => format!("Deleted playlist **{}**.", name),
+                Err(e) => describe_error(e, name),
+            }),
+        "list" => show_playlist_report(&ctx, guild_id, &command, name).await,
+        "play" => {
+            let shuffle = get_bool_option(options, "shuffle");
+            let looping = get_bool_option(options, "loop");
+            play_report(&ctx, guild_id, &command, name, shuffle, looping).await
+        }
+        _ => return,
+    };
+
+    send_result(&ctx, &command, result).await;
+}
+
+async fn send_result(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    result: Result<String>,
+) {
+    match result {
+        Ok(message) => respond(ctx, command, message).await,
+        Err(e) => {
+            error!("Error handling playlist command: {}", e);
+            respond(ctx, command, "Something went wrong with that playlist.").await;
+        }
+    }
+}
+
+async fn list_playlists_report(
+    ctx: &Context,
+    guild_id: GuildId,
+    command: &ApplicationCommandInteraction,
+) -> Result<String> {
+    let (personal, shared) = get_playlist_names(ctx, guild_id, command.user.id).await?;
+    if personal.is_empty() && shared.is_empty() {
+        return Ok("There are no playlists yet. Create one with `/playlist create`.".to_string());
+    }
+
+    let mut output = String::new();
+    if !personal.is_empty() {
+        output.push_str(&format!("Your playlists: {}\n", personal.join(", ")));
+    }
+    if !shared.is_empty() {
+        output.push_str(&format!("Shared playlists: {}\n", shared.join(", ")));
+    }
+
+    Ok(output)
+}
+
+async fn show_playlist_report(
+    ctx: &Context,
+    guild_id: GuildId,
+    command: &ApplicationCommandInteraction,
+    name: &str,
+) -> Result<String> {
+    let playlist = match get_playlist(ctx, guild_id, command.user.id, name).await? {
+        Some((_, playlist)) => playlist,
+        None => return Ok(describe_error(PlaylistError::NotFound, name)),
+    };
+    if playlist.items.is_empty() {
+        return Ok(format!("**{}** is empty.", name));
+    }
+
+    let mut output = format!("**{}**:\n", name);
+    for (index, item) in playlist.items.iter().enumerate() {
+        output.push_str(&format!("`{}.` {}\n", index + 1, item));
+    }
+
+    Ok(output)
+}
+
+async fn play_report(
+    ctx: &Context,
+    guild_id: GuildId,
+    command: &ApplicationCommandInteraction,
+    name: &str,
+    shuffle: bool,
+    looping: bool,
+) -> Result<String> {
+    let playlist = match get_playlist(ctx, guild_id, command.user.id, name).await? {
+        Some((_, playlist)) => playlist,
+        None => return Ok(describe_error(PlaylistError::NotFound, name)),
+    };
+    if playlist.items.is_empty() {
+        return Ok(format!("**{}** is empty.", name));
+    }
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        join_channel(ctx, guild_id, channel_id).await?;
+    }
+
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    let length = playlist.items.len();
+    play_playlist(ctx, guild_id, playlist, shuffle, looping, origin);
+
+    Ok(format!("Queued {} items of **{}**.", length, name))
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel},
+    quiz::{get_scoreboard, start_quiz, stop_quiz, DEFAULT_ROUNDS, MAX_ROUNDS},
+};
+
+use super::{announce, get_option, get_string_option, respond};
+
+pub const QUIZ_COMMAND: &str = "quiz";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(QUIZ_COMMAND)
+        .description("Guess the sound Pascal plays")
+        .create_option(|option| {
+            option
+                .name("start")
+                .description("Start a quiz in this channel")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("rounds")
+                        .description("How many sounds to guess, 5 if left out")
+                        .kind(ApplicationCommandOptionType::Integer)
+                        .required(false)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("category")
+                        .description("Only play sounds of this category")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("stop")
+                .description("Stop the running quiz")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("scores")
+                .description("Show the best quiz players")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub async fn handle_quiz_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+
+    match subcommand.name.as_str() {
+        "start" => handle_start(&ctx, &command, guild_id, &subcommand.options).await,
+        "stop" => match stop_quiz(&ctx, guild_id).await {
+            Ok(true) => announce(&ctx, &command, "Stopping the quiz after this round.").await,
+            Ok(false) => respond(&ctx, &command, "There is no quiz running.").await,
+            Err(e) => {
+                error!("Error stopping quiz: {}", e);
+                respond(&ctx, &command, "Could not stop the quiz.").await;
+            }
+        },
+        "scores" => match scores_report(&ctx, guild_id).await {
+            Ok(report) => respond(&ctx, &command, report).await,
+            Err(e) => {
+                error!("Error fetching quiz scores: {}", e);
+                respond(&ctx, &command, "Could not fetch the scores.").await;
+            }
+        },
+        _ => (),
+    }
+}
+
+async fn handle_start(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) {
+    let channel_id = match get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        Some(channel_id) => channel_id,
+        None => {
+            respond(ctx, command, "Please join a voice channel first.").await;
+            return;
+        }
+    };
+
+    let rounds = match get_option(options, "rounds") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(rounds)) => {
+            (*rounds).clamp(1, MAX_ROUNDS as i64) as usize
+        }
+        _ => DEFAULT_ROUNDS,
+    };
+    let category = get_string_option(options, "category");
+
+    match start_quiz(
+        ctx,
+        guild_id,
+        command.channel_id,
+        command.user.id,
+        rounds,
+        category,
+    )
+    .await
+    {
+        Ok(Ok(rounds)) => {
+            // Only join once the quiz started, so a second start can't move Pascal away from
+            // a running quiz. The first round waits a moment, giving Pascal time to join.
+            if let Err(e) = join_channel(ctx, guild_id, channel_id).await {
+                error!("Failed to join channel: {}", e);
+                if let Err(e) = stop_quiz(ctx, guild_id).await {
+                    error!("Error stopping quiz: {}", e);
+                }
+                respond(ctx, command, "Could not join your voice channel.").await;
+                return;
+            }
+
+            let message = format!(
+                "🎲 Quiz time! {} rounds, type the name of the sound you hear. Faster answers score more points.",
+                rounds
+            );
+            announce(ctx, command, message).await;
+        }
+        Ok(Err(reason)) => respond(ctx, command, reason).await,
+        Err(e) => {
+            error!("Error starting quiz: {}", e);
+            respond(ctx, command, "Could not start the quiz.").await;
+        }
+    }
+}
+
+async fn scores_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let scoreboard = get_scoreboard(ctx, guild_id).await?;
+    if scoreboard.is_empty() {
+        return Ok("Nobody has scored in a quiz yet.".to_string());
+    }
+
+    let mut output = String::from("Best quiz players:\n");
+    for (place, (user_id, points)) in scoreboard.iter().enumerate() {
+        output.push_str(&format!(
+            "{}. <@{}>: {} points\n",
+            place + 1,
+            user_id,
+            points
+        ));
+    }
+
+    Ok(output)
+}
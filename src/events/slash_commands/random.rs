@@ -0,0 +1,72 @@
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel, play_from_file},
+    history::{PlayInterface, PlayOrigin},
+    random::pick_random_sound,
+};
+
+use super::{announce, get_option, respond};
+
+pub const RANDOM_COMMAND: &str = "random";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(RANDOM_COMMAND)
+        .description("Play a random sound")
+        .create_option(|option| {
+            option
+                .name("filter")
+                .description("Only pick from this category or from sounds containing this text")
+                .kind(ApplicationCommandOptionType::String)
+                .required(false)
+        })
+}
+
+pub async fn handle_random_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let filter = match get_option(&command.data.options, "filter") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(filter)) => Some(filter.as_str()),
+        _ => None,
+    };
+
+    let sound_name = match pick_random_sound(&ctx, guild_id, filter).await {
+        Ok(Some(sound_name)) => sound_name,
+        Ok(None) => {
+            respond(&ctx, &command, "No sounds match that filter.").await;
+            return;
+        }
+        Err(e) => {
+            error!("Error picking random sound: {}", e);
+            respond(&ctx, &command, "Something went wrong picking a sound.").await;
+            return;
+        }
+    };
+
+    if let Some(channel_id) = get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        if let Err(e) = join_channel(&ctx, guild_id, channel_id).await {
+            error!("Failed to join channel: {}", e);
+        }
+    }
+
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    if let Err(e) = play_from_file(&ctx, command.channel_id, guild_id, &sound_name, origin).await {
+        error!("Failed to play random sound: {}", e);
+    }
+
+    announce(&ctx, &command, format!("🎲 Playing **{}**", sound_name)).await;
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    combos::get_combo,
+    reactions::{delete_reaction_sound, get_reaction_sounds, parse_emoji, save_reaction_sound},
+    sound_files::get_sound_files,
+};
+
+use super::{get_string_option, is_admin, respond};
+
+pub const REACTION_COMMAND: &str = "reaction";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(REACTION_COMMAND)
+        .description("Play sounds when someone reacts with an emoji")
+        .create_option(|option| {
+            option
+                .name("add")
+                .description("Play a sound whenever someone reacts with the emoji")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("emoji")
+                        .description("The emoji to react with")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Sound or combo to play")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Stop playing a sound for the emoji")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("emoji")
+                        .description("The emoji to react with")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List the emojis that play sounds")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub async fn handle_reaction_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+
+    let result = match subcommand.name.as_str() {
+        "add" | "remove" if !is_admin(&command) => {
+            Ok("Only admins can change the reaction sounds.".to_string())
+        }
+        "add" => add_report(&ctx, &command, guild_id, options).await,
+        "remove" => remove_report(&ctx, guild_id, options).await,
+        "list" => list_report(&ctx, guild_id).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error handling reaction command: {}", e);
+            respond(&ctx, &command, "Something went wrong with that reaction.").await;
+        }
+    }
+}
+
+async fn add_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String> {
+    let (emoji, sound) = match (
+        get_string_option(options, "emoji"),
+        get_string_option(options, "sound"),
+    ) {
+        (Some(emoji), Some(sound)) => (emoji, sound),
+        _ => return Ok("Please give me an emoji and a sound.".to_string()),
+    };
+
+    let key = match parse_emoji(emoji) {
+        Ok(key) => key,
+        Err(reason) => return Ok(reason),
+    };
+    if !get_sound_files()?.contains_key(sound) && get_combo(ctx, guild_id, sound).await?.is_none() {
+        return Ok(format!("I don't know the sound **{}**.", sound));
+    }
+
+    save_reaction_sound(ctx, guild_id, key, emoji, sound, command.user.id).await?;
+
+    Ok(format!(
+        "Reacting with {} now plays **{}**.",
+        emoji.trim(),
+        sound
+    ))
+}
+
+async fn remove_report(
+    ctx: &Context,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String> {
+    let emoji = match get_string_option(options, "emoji") {
+        Some(emoji) => emoji,
+        None => return Ok("Please give me an emoji.".to_string()),
+    };
+    let key = match parse_emoji(emoji) {
+        Ok(key) => key,
+        Err(reason) => return Ok(reason),
+    };
+
+    Ok(if delete_reaction_sound(ctx, guild_id, &key).await? {
+        format!("Reacting with {} no longer plays a sound.", emoji.trim())
+    } else {
+        format!("{} doesn't play a sound.", emoji.trim())
+    })
+}
+
+async fn list_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let reactions = get_reaction_sounds(ctx, guild_id).await?;
+    if reactions.is_empty() {
+        return Ok("No emojis play sounds yet.".to_string());
+    }
+
+    let mut output = String::from("Reaction sounds:\n");
+    for reaction in reactions {
+        output.push_str(&format!(
+            "\t- {} plays **{}**\n",
+            reaction.emoji, reaction.sound
+        ));
+    }
+
+    Ok(output)
+}
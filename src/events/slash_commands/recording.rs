@@ -0,0 +1,212 @@
+use anyhow::Result;
+use chrono::Local;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    guild_settings::{get_guild_settings, update_guild_settings},
+    idle::get_connected_channel,
+    recording::{
+        get_buffer_secs, get_recorded_channel, save_recording, start_recording, stop_recording,
+    },
+};
+
+use super::{announce, defer, edit_response, get_option, get_string_option, is_admin, respond};
+
+pub const RECORDING_COMMAND: &str = "recording";
+pub const CLIP_COMMAND: &str = "clip";
+
+pub fn create_recording_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(RECORDING_COMMAND)
+        .description("Let Pascal keep the last seconds of voice chat, so they can be clipped")
+        .create_option(|option| {
+            option
+                .name("on")
+                .description("Record voice channels Pascal is in, announced in this channel")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("off")
+                .description("Stop recording and throw away what was recorded")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("status")
+                .description("Show whether Pascal is recording")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub fn create_clip_command(
+    command: &mut CreateApplicationCommand,
+) -> &mut CreateApplicationCommand {
+    command
+        .name(CLIP_COMMAND)
+        .description("Save what was just said in Pascal's voice channel as a new sound")
+        .create_option(|option| {
+            option
+                .name("seconds")
+                .description(
+                    "How many of the last seconds to save, everything recorded if left out",
+                )
+                .kind(ApplicationCommandOptionType::Integer)
+                .required(false)
+        })
+        .create_option(|option| {
+            option
+                .name("name")
+                .description("Name of the new sound, named after the current time if left out")
+                .kind(ApplicationCommandOptionType::String)
+                .required(false)
+        })
+}
+
+pub async fn handle_recording_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+
+    match subcommand.name.as_str() {
+        "on" | "off" if !is_admin(&command) => {
+            respond(&ctx, &command, "Only admins can turn recording on or off.").await
+        }
+        "on" => match enable_recording(&ctx, &command, guild_id).await {
+            Ok(()) => {
+                announce(
+                    &ctx,
+                    &command,
+                    "🔴 Recording is on. Pascal keeps the last seconds of voice chat in its channel and announces here whenever it starts recording.",
+                )
+                .await
+            }
+            Err(e) => {
+                error!("Error turning recording on: {}", e);
+                respond(&ctx, &command, "Could not turn recording on.").await;
+            }
+        },
+        "off" => match disable_recording(&ctx, guild_id).await {
+            Ok(()) => {
+                announce(
+                    &ctx,
+                    &command,
+                    "⚪ Recording is off, everything recorded so far was thrown away.",
+                )
+                .await
+            }
+            Err(e) => {
+                error!("Error turning recording off: {}", e);
+                respond(&ctx, &command, "Could not turn recording off.").await;
+            }
+        },
+        "status" => match status_report(&ctx, guild_id).await {
+            Ok(report) => respond(&ctx, &command, report).await,
+            Err(e) => {
+                error!("Error fetching recording status: {}", e);
+                respond(&ctx, &command, "Could not fetch the recording status.").await;
+            }
+        },
+        _ => (),
+    }
+}
+
+async fn enable_recording(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+) -> Result<()> {
+    update_guild_settings(ctx, guild_id, |settings| {
+        settings.recording_channel = Some(*command.channel_id.as_u64())
+    })
+    .await?;
+
+    match get_connected_channel(ctx, guild_id).await {
+        Some(channel_id) => start_recording(ctx, guild_id, channel_id).await,
+        None => Ok(()),
+    }
+}
+
+async fn disable_recording(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    update_guild_settings(ctx, guild_id, |settings| settings.recording_channel = None).await?;
+    stop_recording(ctx, guild_id).await?;
+
+    Ok(())
+}
+
+async fn status_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let announcement_channel = match get_guild_settings(ctx, guild_id).await?.recording_channel {
+        Some(announcement_channel) => announcement_channel,
+        None => return Ok("Recording is off.".to_string()),
+    };
+    let buffer_secs = get_buffer_secs(ctx).await?;
+
+    Ok(match get_recorded_channel(ctx, guild_id).await? {
+        Some(channel_id) => format!(
+            "🔴 Pascal is recording <#{}> and keeps the last {} seconds. Recording is announced in <#{}>.",
+            channel_id, buffer_secs, announcement_channel
+        ),
+        None => format!(
+            "Recording is on, but Pascal isn't in a voice channel. Recording is announced in <#{}>.",
+            announcement_channel
+        ),
+    })
+}
+
+pub async fn handle_clip_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let buffer_secs = match get_buffer_secs(&ctx).await {
+        Ok(buffer_secs) => buffer_secs,
+        Err(e) => {
+            error!("Error fetching recording config: {}", e);
+            respond(&ctx, &command, "Could not save the clip.").await;
+            return;
+        }
+    };
+    let seconds = match get_option(&command.data.options, "seconds") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(seconds)) => {
+            ((*seconds).max(1) as u64).min(buffer_secs)
+        }
+        _ => buffer_secs,
+    };
+    let name = match get_string_option(&command.data.options, "name") {
+        Some(name) => name.trim().to_owned(),
+        None => format!("clip-{}", Local::now().format("%Y%m%d-%H%M%S")),
+    };
+
+    // Encoding the clip can take longer than Discord waits for an answer
+    defer(&ctx, &command, false).await;
+
+    match save_recording(&ctx, guild_id, seconds, &name).await {
+        Ok(Ok(())) => {
+            let message = format!("🎬 Saved the last {} seconds as **{}**.", seconds, name);
+            edit_response(&ctx, &command, message).await
+        }
+        Ok(Err(reason)) => edit_response(&ctx, &command, reason).await,
+        Err(e) => {
+            error!("Error saving clip: {}", e);
+            edit_response(&ctx, &command, "Could not save the clip.").await;
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel},
+    history::{PlayInterface, PlayOrigin},
+    tts::speak,
+};
+
+use super::{defer, edit_response, get_string_option, respond};
+
+pub const SAY_COMMAND: &str = "say";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(SAY_COMMAND)
+        .description("Make Pascal say something in your voice channel")
+        .create_option(|option| {
+            option
+                .name("text")
+                .description("What to say")
+                .kind(ApplicationCommandOptionType::String)
+                .required(true)
+        })
+}
+
+pub async fn handle_say_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let text = match get_string_option(&command.data.options, "text") {
+        Some(text) => text.to_owned(),
+        None => return,
+    };
+
+    let channel_id = match get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+        Some(channel_id) => channel_id,
+        None => {
+            respond(&ctx, &command, "Please join a voice channel first.").await;
+            return;
+        }
+    };
+
+    // Synthesizing can take longer than Discord waits for an answer
+    defer(&ctx, &command, true).await;
+
+    if let Err(e) = join_channel(&ctx, guild_id, channel_id).await {
+        error!("Failed to join channel: {}", e);
+    }
+
+    let origin = PlayOrigin::new(command.user.id, PlayInterface::SlashCommand);
+    match speak(&ctx, guild_id, &text, origin).await {
+        Ok(Ok(_)) => edit_response(&ctx, &command, "Tight.").await,
+        Ok(Err(reason)) => edit_response(&ctx, &command, reason).await,
+        Err(e) => {
+            error!("Error speaking text: {}", e);
+            edit_response(&ctx, &command, "Could not say that.").await;
+        }
+    }
+}
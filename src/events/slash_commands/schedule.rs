@@ -0,0 +1,185 @@
+use anyhow::Result;
+use chrono::Local;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        channel::ChannelType,
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    discord::get_channel_of_member,
+    schedule::{add_schedule, get_schedules, remove_schedule, ScheduleTime},
+    sound_files::get_sound_files,
+};
+
+use super::{get_option, get_string_option, is_admin, respond};
+
+pub const SCHEDULE_COMMAND: &str = "schedule";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(SCHEDULE_COMMAND)
+        .description("Play sounds at a set time or again and again")
+        .create_option(|option| {
+            option
+                .name("add")
+                .description("Schedule a sound for a voice channel")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Sound to play")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("when")
+                        .description("A time like 17:00 or 2021-12-24 18:00, or cron like 0 17 * * fri or @hourly")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("channel")
+                        .description("Voice channel to play in, yours if left out")
+                        .kind(ApplicationCommandOptionType::Channel)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Remove a scheduled sound")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("id")
+                        .description("ID of the scheduled sound, see the list")
+                        .kind(ApplicationCommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List the scheduled sounds")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub async fn handle_schedule_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+
+    let result = match subcommand.name.as_str() {
+        "add" | "remove" if !is_admin(&command) => {
+            Ok("Only admins can change the scheduled sounds.".to_string())
+        }
+        "add" => add_report(&ctx, &command, guild_id, options).await,
+        "remove" => match get_option(options, "id") {
+            Some(ApplicationCommandInteractionDataOptionValue::Integer(id)) => {
+                remove_schedule(&ctx, guild_id, (*id).max(0) as u64)
+                    .await
+                    .map(|removed| {
+                        if removed {
+                            format!("Removed scheduled sound **#{}**.", id)
+                        } else {
+                            format!("There is no scheduled sound **#{}**.", id)
+                        }
+                    })
+            }
+            _ => Ok("Please give me the ID of the scheduled sound.".to_string()),
+        },
+        "list" => list_report(&ctx, guild_id).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error handling schedule command: {}", e);
+            respond(&ctx, &command, "Something went wrong with that schedule.").await;
+        }
+    }
+}
+
+async fn add_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String> {
+    let (sound, when) = match (
+        get_string_option(options, "sound"),
+        get_string_option(options, "when"),
+    ) {
+        (Some(sound), Some(when)) => (sound, when),
+        _ => return Ok("Please give me a sound and when to play it.".to_string()),
+    };
+
+    if !get_sound_files()?.contains_key(sound) {
+        return Ok(format!("I don't know the sound **{}**.", sound));
+    }
+
+    let when = match ScheduleTime::parse(when, Local::now()) {
+        Ok(when) => when,
+        Err(reason) => return Ok(reason),
+    };
+
+    let channel_id = match get_option(options, "channel") {
+        Some(ApplicationCommandInteractionDataOptionValue::Channel(channel)) => {
+            if channel.kind != ChannelType::Voice {
+                return Ok("Please pick a voice channel.".to_string());
+            }
+            channel.id
+        }
+        _ => match get_channel_of_member(ctx.clone(), guild_id, command.user.id).await {
+            Some(channel_id) => channel_id,
+            None => return Ok("Please join a voice channel or pick one.".to_string()),
+        },
+    };
+
+    let description = when.to_string();
+    Ok(
+        match add_schedule(ctx, guild_id, channel_id, sound, when, command.user.id).await? {
+            Ok(id) => format!(
+                "Scheduled **{}** in <#{}> for {} as **#{}**.",
+                sound, channel_id, description, id
+            ),
+            Err(reason) => reason,
+        },
+    )
+}
+
+async fn list_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let schedules = get_schedules(ctx, guild_id).await?;
+    if schedules.is_empty() {
+        return Ok("There are no scheduled sounds yet.".to_string());
+    }
+
+    let mut output = String::from("Scheduled sounds:\n");
+    for schedule in schedules {
+        output.push_str(&format!(
+            "\t- **#{}**: **{}** in <#{}> at {}\n",
+            schedule.id, schedule.sound, schedule.channel_id, schedule.when
+        ));
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    effects::AudioOptions,
+    import::{get_sound_source, import_sound, SoundSource},
+    sound_files::get_sound_files,
+    tracks::format_timestamp,
+};
+
+use super::{apply_trim_options, defer, edit_response, get_string_option, is_admin, respond};
+
+pub const SOUND_COMMAND: &str = "sound";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(SOUND_COMMAND)
+        .description("Manage the sound library")
+        .create_option(|option| {
+            option
+                .name("import")
+                .description("Download a clip from a URL into the sound library")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("url")
+                        .description("URL of the video or audio file")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Name to play the new sound by")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("start")
+                        .description("Position the clip starts at, e.g. 1:23 or 4.5")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("end")
+                        .description("Position the clip ends at, e.g. 1:30 or 12")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("info")
+                .description("Show where a sound came from")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Name of the sound")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+}
+
+pub async fn handle_sound_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+
+    match subcommand.name.as_str() {
+        // The library is shared by every server Pascal is in
+        "import" if !is_admin(&command) => {
+            respond(&ctx, &command, "Only admins can import sounds.").await
+        }
+        "import" => handle_import(&ctx, &command, guild_id, options).await,
+        "info" => {
+            let name = get_string_option(options, "name").unwrap_or_default();
+            let message = match get_sound_source(&ctx, name).await {
+                Ok(Some(source)) => source_report(name, &source),
+                Ok(None) if get_sound_files().map_or(false, |files| files.contains_key(name)) => {
+                    format!("**{}** was added to the library by hand.", name)
+                }
+                Ok(None) => format!("I don't know this sound: **{}**", name),
+                Err(e) => {
+                    error!("Error fetching source of {}: {}", name, e);
+                    "Could not look up that sound.".to_string()
+                }
+            };
+            respond(&ctx, &command, message).await;
+        }
+        _ => (),
+    }
+}
+
+async fn handle_import(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) {
+    let url = get_string_option(options, "url").unwrap_or_default();
+    let name = get_string_option(options, "name").unwrap_or_default();
+
+    let mut trim = AudioOptions::default();
+    if let Err(err) = apply_trim_options(options, &mut trim) {
+        respond(ctx, command, err).await;
+        return;
+    }
+
+    // Downloading and converting easily takes longer than Discord waits for a response
+    defer(ctx, command, false).await;
+
+    let message = match import_sound(ctx, guild_id, url, name, &trim, command.user.id).await {
+        Ok(Ok(_)) => format!("📥 Imported **{}** from <{}>", name, url),
+        Ok(Err(reason)) => reason,
+        Err(e) => {
+            error!("Error importing {} from {}: {}", name, url, e);
+            "Could not import that sound.".to_string()
+        }
+    };
+    edit_response(ctx, command, message).await;
+}
+
+fn source_report(name: &str, source: &SoundSource) -> String {
+    let mut output = format!("**{}** was imported from <{}>", name, source.url);
+    if let Some(title) = &source.title {
+        output.push_str(&format!(" ({})", title));
+    }
+
+    let position = |ms: u64| format_timestamp(Duration::from_millis(ms));
+    match (source.start_ms, source.end_ms) {
+        (Some(start), Some(end)) => {
+            output.push_str(&format!(", {} to {}", position(start), position(end)))
+        }
+        (Some(start), None) => output.push_str(&format!(", from {}", position(start))),
+        (None, Some(end)) => output.push_str(&format!(", up to {}", position(end))),
+        (None, None) => (),
+    }
+
+    output.push_str(&format!(
+        "\nImported by <@{}> <t:{}:R>",
+        source.imported_by, source.imported_at
+    ));
+
+    output
+}
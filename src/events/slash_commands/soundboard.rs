@@ -0,0 +1,104 @@
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::soundboard::{refresh_panels, remove_panels, setup_panels};
+
+use super::{get_option, is_admin, respond};
+
+pub const SOUNDBOARD_COMMAND: &str = "soundboard";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(SOUNDBOARD_COMMAND)
+        .description("Manage the persistent soundboard panels")
+        .create_option(|option| {
+            option
+                .name("setup")
+                .description("Post soundboard panels into a channel and keep them up to date")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("channel")
+                        .description("Channel to keep the soundboard panels in")
+                        .kind(ApplicationCommandOptionType::Channel)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("refresh")
+                .description("Recreate missing panels and update them with the current sounds")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Delete this server's soundboard panels")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub async fn handle_soundboard_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    if !is_admin(&command) {
+        respond(&ctx, &command, "Only admins can manage the soundboard.").await;
+        return;
+    }
+
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+
+    let result = match subcommand.name.as_str() {
+        "setup" => match get_option(&subcommand.options, "channel") {
+            Some(ApplicationCommandInteractionDataOptionValue::Channel(channel)) => {
+                setup_panels(&ctx, guild_id, channel.id)
+                    .await
+                    .map(|_| format!("Soundboard is now kept in <#{}>.", channel.id))
+            }
+            _ => Ok("Please pick a text channel.".to_string()),
+        },
+        "refresh" => refresh_panels(&ctx, guild_id).await.map(|refreshed| {
+            if refreshed {
+                "Soundboard panels refreshed.".to_string()
+            } else {
+                "This server has no soundboard panels.".to_string()
+            }
+        }),
+        "remove" => remove_panels(&ctx, guild_id).await.map(|removed| {
+            if removed {
+                "Soundboard panels removed.".to_string()
+            } else {
+                "This server has no soundboard panels.".to_string()
+            }
+        }),
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(err) => {
+            error!("Error handling soundboard command: {}", err);
+            respond(
+                &ctx,
+                &command,
+                "Something went wrong managing the soundboard.",
+            )
+            .await;
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::{GuildId, UserId},
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+            ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    media_cache::get_cache_metrics,
+    stats::{get_sound_stats, get_top_sounds, get_unplayed_sounds, get_user_stats},
+};
+
+use super::{get_option, respond};
+
+pub const STATS_COMMAND: &str = "stats";
+
+const LEADERBOARD_SIZE: usize = 10;
+// Stay well below Discord's message length limit
+const MAX_UNPLAYED_LISTED: usize = 50;
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(STATS_COMMAND)
+        .description("Show play statistics")
+        .create_option(|option| {
+            option
+                .name("top")
+                .description("Most played sounds on this server")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("user")
+                .description("Play statistics of a user")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("user")
+                        .description("User to show statistics for")
+                        .kind(ApplicationCommandOptionType::User)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("sound")
+                .description("Play statistics of a sound")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Name of the sound")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("unplayed")
+                .description("Sounds that have never been played")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("cache")
+                .description("How well the download cache for URLs is doing")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub async fn handle_stats_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+
+    let result = match subcommand.name.as_str() {
+        "top" => top_sounds_report(&ctx, guild_id).await,
+        "user" => match get_option(&subcommand.options, "user") {
+            Some(ApplicationCommandInteractionDataOptionValue::User(user, _)) => {
+                user_report(&ctx, guild_id, user.id).await
+            }
+            _ => Ok("Please pick a user.".to_string()),
+        },
+        "sound" => match get_option(&subcommand.options, "sound") {
+            Some(ApplicationCommandInteractionDataOptionValue::String(sound)) => {
+                sound_report(&ctx, guild_id, sound).await
+            }
+            _ => Ok("Please name a sound.".to_string()),
+        },
+        "unplayed" => unplayed_report(&ctx).await,
+        "cache" => cache_report(&ctx).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(report) => respond(&ctx, &command, report).await,
+        Err(e) => {
+            error!("Error fetching statistics: {}", e);
+            respond(
+                &ctx,
+                &command,
+                "Something went wrong fetching the statistics.",
+            )
+            .await;
+        }
+    }
+}
+
+async fn top_sounds_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let top_sounds = get_top_sounds(ctx, guild_id, LEADERBOARD_SIZE).await?;
+    if top_sounds.is_empty() {
+        return Ok("Nothing has been played yet.".to_string());
+    }
+
+    let mut output = String::from("Most played sounds:\n");
+    for (index, (sound, plays)) in top_sounds.iter().enumerate() {
+        output.push_str(&format!(
+            "`{}.` **{}** - {} plays\n",
+            index + 1,
+            sound,
+            plays
+        ));
+    }
+
+    Ok(output)
+}
+
+async fn user_report(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Result<String> {
+    let stats = get_user_stats(ctx, guild_id, user_id, LEADERBOARD_SIZE).await?;
+    if stats.plays == 0 {
+        return Ok(format!("<@{}> hasn't played anything yet.", user_id));
+    }
+
+    let mut output = format!(
+        "<@{}> played {} sounds. Favourites:\n",
+        user_id, stats.plays
+    );
+    for (index, (sound, plays)) in stats.top_sounds.iter().enumerate() {
+        output.push_str(&format!(
+            "`{}.` **{}** - {} plays\n",
+            index + 1,
+            sound,
+            plays
+        ));
+    }
+
+    Ok(output)
+}
+
+async fn sound_report(ctx: &Context, guild_id: GuildId, sound: &str) -> Result<String> {
+    let stats = match get_sound_stats(ctx, guild_id, sound).await? {
+        Some(stats) => stats,
+        None => return Ok(format!("**{}** has never been played here.", sound)),
+    };
+
+    let mut output = format!(
+        "**{}** was played {} times by {} users, last <t:{}:R>.",
+        sound, stats.plays, stats.users, stats.last_played
+    );
+    if let Some((user_id, plays)) = stats.top_user {
+        output.push_str(&format!(
+            "\nBiggest fan: <@{}> with {} plays",
+            user_id, plays
+        ));
+    }
+
+    Ok(output)
+}
+
+async fn unplayed_report(ctx: &Context) -> Result<String> {
+    let unplayed = get_unplayed_sounds(ctx).await?;
+    if unplayed.is_empty() {
+        return Ok("Every sound has been played at least once.".to_string());
+    }
+
+    let mut output = format!("{} sounds have never been played:\n", unplayed.len());
+    for sound in unplayed.iter().take(MAX_UNPLAYED_LISTED) {
+        output.push_str(&format!("\t- {}\n", sound));
+    }
+    if unplayed.len() > MAX_UNPLAYED_LISTED {
+        output.push_str(&format!(
+            "...and {} more\n",
+            unplayed.len() - MAX_UNPLAYED_LISTED
+        ));
+    }
+
+    Ok(output)
+}
+
+async fn cache_report(ctx: &Context) -> Result<String> {
+    let metrics = get_cache_metrics(ctx).await?;
+    let lookups = metrics.hits + metrics.misses;
+    let hit_rate = if lookups > 0 {
+        metrics.hits as f64 / lookups as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(format!(
+        "Download cache since the last restart:\n\
+        \t- {} hits, {} misses ({:.0}% hit rate)\n\
+        \t- {} evictions\n\
+        \t- {} cached URLs taking up {:.1} MiB",
+        metrics.hits,
+        metrics.misses,
+        hit_rate,
+        metrics.evictions,
+        metrics.entries,
+        metrics.size as f64 / (1024.0 * 1024.0)
+    ))
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::GuildId,
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    combos::get_combo,
+    sound_files::get_sound_files,
+    timers::{cancel_timer, get_timers, parse_timer_duration, start_timer},
+    tracks::format_timestamp,
+};
+
+use super::{get_option, get_string_option, respond};
+
+pub const TIMER_COMMAND: &str = "timer";
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(TIMER_COMMAND)
+        .description("Play a sound in your voice channel once a timer runs out")
+        .create_option(|option| {
+            option
+                .name("start")
+                .description("Start a timer")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("duration")
+                        .description("How long the timer runs, e.g. 10m, 1h30m or 45s")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Sound to play when the timer runs out")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("message")
+                        .description("Message to post when the timer runs out")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List your running timers")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+        .create_option(|option| {
+            option
+                .name("cancel")
+                .description("Cancel one of your timers")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("id")
+                        .description("ID of the timer, see the list")
+                        .kind(ApplicationCommandOptionType::Integer)
+                        .required(true)
+                })
+        })
+}
+
+pub async fn handle_timer_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+
+    let result = match subcommand.name.as_str() {
+        "start" => start_report(&ctx, &command, guild_id, options).await,
+        "list" => list_report(&ctx, &command, guild_id).await,
+        "cancel" => match get_option(options, "id") {
+            Some(ApplicationCommandInteractionDataOptionValue::Integer(id)) => {
+                cancel_timer(&ctx, guild_id, command.user.id, (*id).max(0) as u64)
+                    .await
+                    .map(|cancelled| {
+                        if cancelled {
+                            format!("Cancelled timer **#{}**.", id)
+                        } else {
+                            format!("You have no timer **#{}**.", id)
+                        }
+                    })
+            }
+            _ => Ok("Please give me the ID of the timer.".to_string()),
+        },
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error handling timer command: {}", e);
+            respond(&ctx, &command, "Something went wrong with that timer.").await;
+        }
+    }
+}
+
+async fn start_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String> {
+    let (duration, sound) = match (
+        get_string_option(options, "duration"),
+        get_string_option(options, "sound"),
+    ) {
+        (Some(duration), Some(sound)) => (duration, sound),
+        _ => return Ok("Please give me a duration and a sound.".to_string()),
+    };
+
+    let duration = match parse_timer_duration(duration) {
+        Ok(duration) => duration,
+        Err(reason) => return Ok(reason),
+    };
+    if !get_sound_files()?.contains_key(sound) && get_combo(ctx, guild_id, sound).await?.is_none() {
+        return Ok(format!("I don't know the sound **{}**.", sound));
+    }
+
+    let message = get_string_option(options, "message");
+    Ok(
+        match start_timer(
+            ctx,
+            guild_id,
+            command.user.id,
+            command.channel_id,
+            duration,
+            sound,
+            message,
+        )
+        .await?
+        {
+            Ok(id) => format!(
+                "⏲️ Timer **#{}** plays **{}** in {}.",
+                id,
+                sound,
+                format_timestamp(duration)
+            ),
+            Err(reason) => reason,
+        },
+    )
+}
+
+async fn list_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+) -> Result<String> {
+    let timers = get_timers(ctx, guild_id, command.user.id).await?;
+    if timers.is_empty() {
+        return Ok("You have no running timers.".to_string());
+    }
+
+    let mut output = String::from("Your timers:\n");
+    for timer in timers {
+        output.push_str(&format!(
+            "\t- **#{}**: **{}** in {}",
+            timer.id,
+            timer.sound,
+            format_timestamp(timer.remaining)
+        ));
+        if let Some(message) = timer.message {
+            output.push_str(&format!(" ({})", message));
+        }
+        output.push('\n');
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::error;
+use serenity::{
+    builder::CreateApplicationCommand,
+    client::Context,
+    model::{
+        id::{ChannelId, GuildId},
+        interactions::application_command::{
+            ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+            ApplicationCommandInteractionDataOptionValue, ApplicationCommandOptionType,
+        },
+    },
+};
+
+use crate::utils::{
+    combos::get_combo,
+    sound_files::get_sound_files,
+    triggers::{delete_trigger, get_triggers, save_trigger, Trigger, TriggerKind},
+};
+
+use super::{get_bool_option, get_option, get_string_option, is_admin, respond};
+
+pub const TRIGGER_COMMAND: &str = "trigger";
+
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(TRIGGER_COMMAND)
+        .description("Play sounds when a chat message contains a keyword")
+        .create_option(|option| {
+            option
+                .name("add")
+                .description("Add a trigger, replacing an existing one of the same name")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Name of the trigger")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("pattern")
+                        .description("Word to listen for, or a regex if regex is set")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("sound")
+                        .description("Sound or combo to play")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("regex")
+                        .description("Treat the pattern as a regex instead of a word")
+                        .kind(ApplicationCommandOptionType::Boolean)
+                        .required(false)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("cooldown")
+                        .description("Seconds before the trigger fires again, 30 if left out")
+                        .kind(ApplicationCommandOptionType::Integer)
+                        .required(false)
+                })
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("channels")
+                        .description(
+                            "Channels to listen in, e.g. #general #memes. This channel if left out",
+                        )
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(false)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("remove")
+                .description("Remove a trigger")
+                .kind(ApplicationCommandOptionType::SubCommand)
+                .create_sub_option(|sub_option| {
+                    sub_option
+                        .name("name")
+                        .description("Name of the trigger")
+                        .kind(ApplicationCommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_option(|option| {
+            option
+                .name("list")
+                .description("List the triggers")
+                .kind(ApplicationCommandOptionType::SubCommand)
+        })
+}
+
+pub async fn handle_trigger_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    guild_id: GuildId,
+) {
+    let subcommand = match command.data.options.get(0) {
+        Some(subcommand) => subcommand,
+        None => return,
+    };
+    let options = &subcommand.options;
+
+    let result = match subcommand.name.as_str() {
+        "add" | "remove" if !is_admin(&command) => {
+            Ok("Only admins can change the triggers.".to_string())
+        }
+        "add" => add_report(&ctx, &command, guild_id, options).await,
+        "remove" => match get_string_option(options, "name") {
+            Some(name) => delete_trigger(&ctx, guild_id, name).await.map(|deleted| {
+                if deleted {
+                    format!("Removed trigger **{}**.", name)
+                } else {
+                    format!("There is no trigger called **{}**.", name)
+                }
+            }),
+            None => Ok("Please name the trigger.".to_string()),
+        },
+        "list" => list_report(&ctx, guild_id).await,
+        _ => return,
+    };
+
+    match result {
+        Ok(message) => respond(&ctx, &command, message).await,
+        Err(e) => {
+            error!("Error handling trigger command: {}", e);
+            respond(&ctx, &command, "Something went wrong with that trigger.").await;
+        }
+    }
+}
+
+/// Reads channel mentions like `<#123>` from the text
+fn parse_channels(text: &str) -> Vec<ChannelId> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            word.trim_start_matches("<#")
+                .trim_end_matches('>')
+                .parse::<u64>()
+                .ok()
+        })
+        .map(ChannelId)
+        .collect()
+}
+
+async fn add_report(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    options: &[ApplicationCommandInteractionDataOption],
+) -> Result<String> {
+    let (name, pattern, sound) = match (
+        get_string_option(options, "name"),
+        get_string_option(options, "pattern"),
+        get_string_option(options, "sound"),
+    ) {
+        (Some(name), Some(pattern), Some(sound)) => (name, pattern, sound),
+        _ => return Ok("Please give the trigger a name, pattern and sound.".to_string()),
+    };
+
+    if !get_sound_files()?.contains_key(sound) && get_combo(ctx, guild_id, sound).await?.is_none() {
+        return Ok(format!("I don't know the sound **{}**.", sound));
+    }
+
+    let kind = if get_bool_option(options, "regex") {
+        TriggerKind::Regex
+    } else {
+        TriggerKind::Word
+    };
+    let cooldown = match get_option(options, "cooldown") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(secs)) => {
+            Duration::from_secs((*secs).max(0) as u64)
+        }
+        _ => DEFAULT_COOLDOWN,
+    };
+    let channels = match get_string_option(options, "channels") {
+        Some(text) => parse_channels(text),
+        None => vec![command.channel_id],
+    };
+    if channels.is_empty() {
+        return Ok("Please mention the channels to listen in, e.g. #general.".to_string());
+    }
+
+    let trigger = match Trigger::new(
+        pattern,
+        kind,
+        sound,
+        cooldown,
+        channels,
+        *command.user.id.as_u64(),
+    ) {
+        Ok(trigger) => trigger,
+        Err(reason) => return Ok(reason),
+    };
+
+    Ok(match save_trigger(ctx, guild_id, name, trigger).await? {
+        Ok(()) => format!(
+            "Saved trigger **{}**, **{}** plays when someone writes `{}`.",
+            name, sound, pattern
+        ),
+        Err(reason) => reason,
+    })
+}
+
+async fn list_report(ctx: &Context, guild_id: GuildId) -> Result<String> {
+    let triggers = get_triggers(ctx, guild_id).await?;
+    if triggers.is_empty() {
+        return Ok("There are no triggers yet.".to_string());
+    }
+
+    let mut output = String::from("Triggers:\n");
+    for (name, trigger) in triggers {
+        let kind = match trigger.kind {
+            TriggerKind::Word => "word",
+            TriggerKind::Regex => "regex",
+        };
+        let channels: Vec<String> = trigger
+            .channels
+            .iter()
+            .map(|channel| format!("<#{}>", channel))
+            .collect();
+        output.push_str(&format!(
+            "\t- **{}**: {} `{}` plays **{}** in {} ({}s cooldown)\n",
+            name,
+            kind,
+            trigger.pattern,
+            trigger.sound,
+            channels.join(", "),
+            trigger.cooldown_secs
+        ));
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,125 @@
+use log::error;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateComponents},
+    client::Context,
+    model::interactions::application_command::{
+        ApplicationCommandInteraction, ApplicationCommandOptionType,
+    },
+};
+
+use crate::utils::{
+    guild_settings::{get_duration_limits, DurationLimits},
+    import::get_downloader,
+    tracks::format_timestamp,
+    youtube_search::{search_youtube, SearchResult, SEARCH_MENU_ID},
+};
+
+use super::{defer, edit_response, get_string_option};
+
+pub const YT_COMMAND: &str = "yt";
+
+// Discord cuts select menu labels and descriptions off at 100 characters
+const MAX_OPTION_TEXT_LENGTH: usize = 100;
+
+pub fn create_command(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+    command
+        .name(YT_COMMAND)
+        .description("Search Youtube and pick a video to queue")
+        .create_option(|option| {
+            option
+                .name("query")
+                .description("What to search for")
+                .kind(ApplicationCommandOptionType::String)
+                .required(true)
+        })
+}
+
+pub async fn handle_yt_command(ctx: Context, command: ApplicationCommandInteraction) {
+    let query = get_string_option(&command.data.options, "query").unwrap_or_default();
+
+    // Searching takes a few seconds, and only the caller needs to see the results
+    defer(&ctx, &command, true).await;
+
+    let downloader = match get_downloader(&ctx).await {
+        Ok(downloader) => downloader,
+        Err(e) => {
+            error!("Error fetching downloader: {}", e);
+            edit_response(&ctx, &command, "Could not search Youtube right now.").await;
+            return;
+        }
+    };
+    let mut results = match search_youtube(&downloader, query).await {
+        Ok(results) => results,
+        Err(e) => {
+            error!("Error searching Youtube for {}: {}", query, e);
+            edit_response(&ctx, &command, "Could not search Youtube right now.").await;
+            return;
+        }
+    };
+
+    // Don't offer videos that would be rejected anyway
+    if let Some(guild_id) = command.guild_id {
+        if let Ok(DurationLimits {
+            url: Some(max_duration),
+            ..
+        }) = get_duration_limits(&ctx, guild_id).await
+        {
+            results.retain(|result| result.duration.map_or(true, |d| d <= max_duration));
+        }
+    }
+
+    if results.is_empty() {
+        edit_response(&ctx, &command, format!("Found nothing for **{}**.", query)).await;
+        return;
+    }
+
+    if let Err(e) = command
+        .edit_original_interaction_response(&ctx.http, |response| {
+            response
+                .content(format!("Results for **{}**, pick one to queue it:", query))
+                .components(|components| build_result_menu(components, &results))
+        })
+        .await
+    {
+        error!("Error showing Youtube search results: {}", e);
+    }
+}
+
+fn build_result_menu<'a>(
+    components: &'a mut CreateComponents,
+    results: &[SearchResult],
+) -> &'a mut CreateComponents {
+    components.create_action_row(|action_row| {
+        action_row.create_select_menu(|menu| {
+            menu.custom_id(SEARCH_MENU_ID)
+                .placeholder("Pick a video")
+                .options(|options| {
+                    for result in results {
+                        options.create_option(|option| {
+                            option
+                                .label(truncate(&result.title))
+                                .value(&result.url)
+                                .description(truncate(&describe(result)))
+                        });
+                    }
+
+                    options
+                })
+        })
+    })
+}
+
+fn describe(result: &SearchResult) -> String {
+    match (&result.uploader, result.duration) {
+        (Some(uploader), Some(duration)) => {
+            format!("{} · {}", format_timestamp(duration), uploader)
+        }
+        (Some(uploader), None) => uploader.clone(),
+        (None, Some(duration)) => format_timestamp(duration),
+        (None, None) => result.url.clone(),
+    }
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(MAX_OPTION_TEXT_LENGTH).collect()
+}
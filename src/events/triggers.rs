@@ -0,0 +1,55 @@
+use log::error;
+use serenity::{client::Context, model::channel::Message};
+
+use crate::utils::{
+    discord::{get_channel_of_member, join_channel, play_from_file_with_options},
+    effects::AudioOptions,
+    history::{PlayInterface, PlayOrigin},
+    triggers::fire_trigger,
+};
+
+/// Plays the sound of the first trigger matching a chat message in the author's voice channel
+pub async fn handle_trigger_message(ctx: Context, msg: Message) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+
+    // Bots could set each other off, and commands are handled by the framework
+    if msg.author.bot || msg.content.starts_with('!') {
+        return;
+    }
+
+    // Nobody would hear it, so don't use up the cooldown
+    let voice_channel_id = match get_channel_of_member(ctx.clone(), guild_id, msg.author.id).await {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+
+    let sound = match fire_trigger(&ctx, guild_id, msg.channel_id, &msg.content).await {
+        Ok(Some(sound)) => sound,
+        Ok(None) => return,
+        Err(err) => {
+            error!("Error matching triggers: {}", err);
+            return;
+        }
+    };
+
+    if let Err(err) = join_channel(&ctx, guild_id, voice_channel_id).await {
+        error!("Failed to join channel: {}", err);
+    }
+
+    let origin = PlayOrigin::new(msg.author.id, PlayInterface::Trigger);
+    if let Err(err) = play_from_file_with_options(
+        &ctx,
+        msg.channel_id,
+        guild_id,
+        &sound,
+        origin,
+        &AudioOptions::default(),
+    )
+    .await
+    {
+        error!("Error playing trigger sound {}: {}", sound, err);
+    }
+}
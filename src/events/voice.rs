@@ -8,7 +8,13 @@ use crate::{
     utils::{
         config::UserIntro,
         discord::{join_channel, play_sound},
+        effects::AudioOptions,
+        follow::follow_voice_state,
+        history::{PlayInterface, PlayOrigin},
+        idle::leave_if_alone,
+        recording::update_recording,
         sound_files::get_sound_files,
+        tts::{get_tts_config, speak},
     },
     IntroStore,
 };
@@ -27,9 +33,22 @@ pub async fn handle_voice_state_update(
         }
     };
 
+    // Whatever Pascal is connected to is what gets recorded, also when someone moves it
+    if new_state.user_id == ctx.cache.current_user_id().await {
+        if let Err(err) = update_recording(&ctx, guild_id, new_state.channel_id).await {
+            error!("Error updating voice recording: {}", err);
+        }
+    }
+
+    // Follow first, so an intro on the followed user's join plays where they are
+    follow_voice_state(&ctx, guild_id, &new_state).await;
+
     if old_state.is_none() && new_state.channel_id.is_some() {
-        handle_voice_channel_intro(ctx, guild_id, new_state).await;
+        handle_voice_channel_intro(ctx.clone(), guild_id, new_state).await;
     }
+
+    // Whoever moved may have been the last listener in Pascal's channel
+    leave_if_alone(&ctx, guild_id).await;
 }
 
 async fn handle_voice_channel_intro(ctx: Context, guild_id: GuildId, new_state: VoiceState) {
@@ -49,16 +68,6 @@ async fn handle_voice_channel_intro(ctx: Context, guild_id: GuildId, new_state:
     };
     let intros = intros_lock.lock().await;
 
-    // Only allowed members
-    if !intros
-        .user_intros
-        .iter()
-        .map(|user_intro| user_intro.user)
-        .any(|x| x == *new_state.user_id.as_u64())
-    {
-        return;
-    }
-
     // Only care for updates in allowed channels
     if new_state.channel_id.is_none()
         || !intros
@@ -68,6 +77,18 @@ async fn handle_voice_channel_intro(ctx: Context, guild_id: GuildId, new_state:
         return;
     }
 
+    // Members without an intro sound may get greeted by speech instead
+    if !intros
+        .user_intros
+        .iter()
+        .map(|user_intro| user_intro.user)
+        .any(|x| x == *new_state.user_id.as_u64())
+    {
+        drop(intros);
+        handle_tts_intro(ctx, guild_id, new_state).await;
+        return;
+    }
+
     let intro_file: &str = intros
         .user_intros
         .iter()
@@ -101,7 +122,10 @@ async fn handle_voice_channel_intro(ctx: Context, guild_id: GuildId, new_state:
         intro_file
     )) {
         Ok(file) => {
-            if let Err(err) = play_sound(&ctx, guild_id, file).await {
+            let origin = PlayOrigin::new(new_state.user_id, PlayInterface::Intro);
+            if let Err(err) =
+                play_sound(&ctx, guild_id, file, origin, &AudioOptions::default()).await
+            {
                 error!("Error playing sound: {}", err);
             }
         }
@@ -110,3 +134,40 @@ async fn handle_voice_channel_intro(ctx: Context, guild_id: GuildId, new_state:
         }
     }
 }
+
+/// Greets the member by name if the speech intro fallback is enabled
+async fn handle_tts_intro(ctx: Context, guild_id: GuildId, new_state: VoiceState) {
+    let config = match get_tts_config(&ctx).await {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{}", err);
+            return;
+        }
+    };
+    if !config.intro_fallback {
+        return;
+    }
+
+    // Pascal's own joins show up here as well
+    let member = match &new_state.member {
+        Some(member) if !member.user.bot => member,
+        _ => return,
+    };
+    let channel_id = match new_state.channel_id {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+
+    if let Err(err) = join_channel(&ctx, guild_id, channel_id).await {
+        error!("Error joining voice channel: {}", err);
+    }
+
+    let name = member.nick.as_ref().unwrap_or(&member.user.name);
+    let greeting = config.intro_template.replace("{name}", name);
+    let origin = PlayOrigin::new(new_state.user_id, PlayInterface::Intro);
+    match speak(&ctx, guild_id, &greeting, origin).await {
+        Ok(Ok(_)) => (),
+        Ok(Err(reason)) => error!("Could not speak intro for {}: {}", name, reason),
+        Err(err) => error!("Error speaking intro: {}", err),
+    }
+}
@@ -1,6 +1,9 @@
 use std::fs;
 use std::sync::Arc;
-use std::{collections::HashSet, env};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
 
 use anyhow::Result;
 use log::{error, info};
@@ -10,10 +13,35 @@ use serenity::{
 use songbird::SerenityInit;
 use utils::config::IntroConfig;
 
+use crate::commands::favorites::quick_slot_shortcut;
 use crate::commands::help::HELP;
 use crate::commands::GENERAL_GROUP;
 use crate::events::handler::Handler;
+use crate::utils::combos::{Combos, CombosStore, COMBOS_STORE_NAME};
 use crate::utils::config::Config;
+use crate::utils::favorites::{Favorites, FavoritesStore, FAVORITES_STORE_NAME};
+use crate::utils::follow::FollowStore;
+use crate::utils::guild_settings::{GuildSettings, GuildSettingsStore, GUILD_SETTINGS_STORE_NAME};
+use crate::utils::history::HistoryStore;
+use crate::utils::idle::ActivityStore;
+use crate::utils::import::{
+    ImportStore, SoundSources, SoundSourcesStore, SOUND_SOURCES_STORE_NAME,
+};
+use crate::utils::media_cache::{CacheIndex, MediaCache, MediaCacheStore, MEDIA_CACHE_STORE_NAME};
+use crate::utils::persistence;
+use crate::utils::playlists::{Playlists, PlaylistsStore, PLAYLISTS_STORE_NAME};
+use crate::utils::quiz::{QuizScores, QuizScoresStore, QuizStore, QUIZ_SCORES_STORE_NAME};
+use crate::utils::random::RandomStore;
+use crate::utils::reactions::{Reactions, ReactionsStore, REACTIONS_STORE_NAME};
+use crate::utils::recording::{RecordingConfigStore, RecordingStore};
+use crate::utils::schedule::{Schedules, SchedulesStore, SCHEDULES_STORE_NAME};
+use crate::utils::soundboard::{PanelStore, SoundboardPanels, PANEL_STORE_NAME};
+use crate::utils::stats::{self, StatsStore};
+use crate::utils::timers::{Timers, TimersStore};
+use crate::utils::tracks::{FadeStore, TrackStore};
+use crate::utils::triggers::{Triggers, TriggersStore, TRIGGERS_STORE_NAME};
+use crate::utils::tts::TtsStore;
+use crate::utils::url_source::UrlStore;
 
 mod commands;
 mod events;
@@ -39,6 +67,102 @@ async fn main() {
         }
     };
 
+    let panels: SoundboardPanels = match persistence::load(PANEL_STORE_NAME) {
+        Ok(panels) => panels,
+        Err(err) => {
+            error!("Unable to load soundboard panels: {}", err);
+            return;
+        }
+    };
+
+    let favorites: Favorites = match persistence::load(FAVORITES_STORE_NAME) {
+        Ok(favorites) => favorites,
+        Err(err) => {
+            error!("Unable to load favorites: {}", err);
+            return;
+        }
+    };
+
+    let combos: Combos = match persistence::load(COMBOS_STORE_NAME) {
+        Ok(combos) => combos,
+        Err(err) => {
+            error!("Unable to load combos: {}", err);
+            return;
+        }
+    };
+
+    let playlists: Playlists = match persistence::load(PLAYLISTS_STORE_NAME) {
+        Ok(playlists) => playlists,
+        Err(err) => {
+            error!("Unable to load playlists: {}", err);
+            return;
+        }
+    };
+
+    let triggers: Triggers = match persistence::load(TRIGGERS_STORE_NAME) {
+        Ok(triggers) => triggers,
+        Err(err) => {
+            error!("Unable to load triggers: {}", err);
+            return;
+        }
+    };
+
+    let reactions: Reactions = match persistence::load(REACTIONS_STORE_NAME) {
+        Ok(reactions) => reactions,
+        Err(err) => {
+            error!("Unable to load reaction sounds: {}", err);
+            return;
+        }
+    };
+
+    let schedules: Schedules = match persistence::load(SCHEDULES_STORE_NAME) {
+        Ok(schedules) => schedules,
+        Err(err) => {
+            error!("Unable to load scheduled sounds: {}", err);
+            return;
+        }
+    };
+
+    let quiz_scores: QuizScores = match persistence::load(QUIZ_SCORES_STORE_NAME) {
+        Ok(quiz_scores) => quiz_scores,
+        Err(err) => {
+            error!("Unable to load quiz scores: {}", err);
+            return;
+        }
+    };
+
+    let sound_sources: SoundSources = match persistence::load(SOUND_SOURCES_STORE_NAME) {
+        Ok(sound_sources) => sound_sources,
+        Err(err) => {
+            error!("Unable to load sound sources: {}", err);
+            return;
+        }
+    };
+
+    let guild_settings: GuildSettings = match persistence::load(GUILD_SETTINGS_STORE_NAME) {
+        Ok(guild_settings) => guild_settings,
+        Err(err) => {
+            error!("Unable to load guild settings: {}", err);
+            return;
+        }
+    };
+
+    let cache_index: CacheIndex = match persistence::load(MEDIA_CACHE_STORE_NAME) {
+        Ok(cache_index) => cache_index,
+        Err(err) => {
+            error!("Unable to load media cache index: {}", err);
+            return;
+        }
+    };
+
+    let stats_db = match stats::open_database() {
+        Ok(conn) => conn,
+        Err(err) => {
+            error!("Unable to open statistics database: {}", err);
+            return;
+        }
+    };
+
     let http = Http::new_with_token(&conf.discord_token);
 
     // Fetch bot's owners and id
@@ -66,6 +190,7 @@ async fn main() {
                 .delimiters(vec![", ", ","])
                 .owners(owners)
         })
+        .unrecognised_command(quick_slot_shortcut)
         .help(&HELP)
         .group(&GENERAL_GROUP);
 
@@ -76,7 +201,8 @@ async fn main() {
         .intents(
             GatewayIntents::GUILD_VOICE_STATES
                 | GatewayIntents::GUILDS
-                | GatewayIntents::GUILD_MESSAGES,
+                | GatewayIntents::GUILD_MESSAGES
+                | GatewayIntents::GUILD_MESSAGE_REACTIONS,
         )
         .register_songbird()
         .await
@@ -86,6 +212,34 @@ async fn main() {
         let mut data = client.data.write().await;
 
         data.insert::<IntroStore>(Arc::new(Mutex::new(conf.intros)));
+        data.insert::<PanelStore>(Arc::new(Mutex::new(panels)));
+        data.insert::<RandomStore>(conf.random);
+        data.insert::<HistoryStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<StatsStore>(Arc::new(Mutex::new(stats_db)));
+        data.insert::<TrackStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<ActivityStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<FollowStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<FavoritesStore>(Arc::new(Mutex::new(favorites)));
+        data.insert::<CombosStore>(Arc::new(Mutex::new(combos)));
+        data.insert::<PlaylistsStore>(Arc::new(Mutex::new(playlists)));
+        data.insert::<TriggersStore>(Arc::new(Mutex::new(triggers)));
+        data.insert::<ReactionsStore>(Arc::new(Mutex::new(reactions)));
+        data.insert::<SchedulesStore>(Arc::new(Mutex::new(schedules)));
+        data.insert::<TimersStore>(Arc::new(Mutex::new(Timers::default())));
+        data.insert::<QuizStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<QuizScoresStore>(Arc::new(Mutex::new(quiz_scores)));
+        data.insert::<ImportStore>(conf.import);
+        data.insert::<UrlStore>(conf.urls);
+        data.insert::<FadeStore>(conf.fades);
+        data.insert::<TtsStore>(conf.tts);
+        data.insert::<RecordingConfigStore>(conf.recording);
+        data.insert::<RecordingStore>(Arc::new(Mutex::new(HashMap::new())));
+        data.insert::<GuildSettingsStore>(Arc::new(Mutex::new(guild_settings)));
+        data.insert::<MediaCacheStore>(Arc::new(Mutex::new(MediaCache::new(
+            conf.cache,
+            cache_index,
+        ))));
+        data.insert::<SoundSourcesStore>(Arc::new(Mutex::new(sound_sources)));
     }
 
     if let Err(err) = client.start().await {
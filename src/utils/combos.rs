@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{
+    error::handle_error,
+    persistence,
+    sequence::{parse_steps, SequenceStep},
+};
+
+pub const COMBOS_STORE_NAME: &str = "combos";
+
+pub struct CombosStore;
+
+impl TypeMapKey for CombosStore {
+    type Value = Arc<Mutex<Combos>>;
+}
+
+/// Named sound sequences saved per guild
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Combos {
+    pub guilds: HashMap<u64, HashMap<String, Combo>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Combo {
+    /// Sequence items as typed, e.g. `["drumroll", "500ms", "tada"]`
+    pub items: Vec<String>,
+    pub gap_ms: u64,
+    pub created_by: u64,
+}
+
+impl Combo {
+    pub fn steps(&self) -> std::result::Result<Vec<SequenceStep>, String> {
+        parse_steps(&self.items)
+    }
+
+    pub fn gap(&self) -> Duration {
+        Duration::from_millis(self.gap_ms)
+    }
+}
+
+async fn get_combos_store(ctx: &Context) -> Result<Arc<Mutex<Combos>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<CombosStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get combos store".to_string()))
+}
+
+pub async fn get_combo(ctx: &Context, guild_id: GuildId, name: &str) -> Result<Option<Combo>> {
+    let store_lock = get_combos_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store
+        .guilds
+        .get(guild_id.as_u64())
+        .and_then(|combos| combos.get(name))
+        .cloned())
+}
+
+/// Returns the guild's combos sorted by name
+pub async fn get_combos(ctx: &Context, guild_id: GuildId) -> Result<Vec<(String, Combo)>> {
+    let store_lock = get_combos_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    let mut combos: Vec<(String, Combo)> = store
+        .guilds
+        .get(guild_id.as_u64())
+        .map(|combos| {
+            combos
+                .iter()
+                .map(|(name, combo)| (name.clone(), combo.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    combos.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(combos)
+}
+
+fn not_creator(name: &str) -> String {
+    format!(
+        "Only whoever saved **{}** or an admin can change or delete it.",
+        name
+    )
+}
+
+/// Saves a combo, replacing an existing combo of the same name.
+/// Combos can only be replaced by their creator or an admin.
+pub async fn save_combo(
+    ctx: &Context,
+    guild_id: GuildId,
+    name: &str,
+    items: Vec<String>,
+    gap: Duration,
+    created_by: UserId,
+    is_admin: bool,
+) -> Result<std::result::Result<(), String>> {
+    let store_lock = get_combos_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let combos = store.guilds.entry(*guild_id.as_u64()).or_default();
+    if let Some(combo) = combos.get(name) {
+        if combo.created_by != *created_by.as_u64() && !is_admin {
+            return Ok(Err(not_creator(name)));
+        }
+    }
+    combos.insert(
+        name.to_owned(),
+        Combo {
+            items,
+            gap_ms: gap.as_millis() as u64,
+            created_by: *created_by.as_u64(),
+        },
+    );
+
+    persistence::save(COMBOS_STORE_NAME, &*store)?;
+
+    Ok(Ok(()))
+}
+
+/// Deletes a combo, which only its creator or an admin may do.
+/// Returns false if there was no combo of that name.
+pub async fn delete_combo(
+    ctx: &Context,
+    guild_id: GuildId,
+    name: &str,
+    user_id: UserId,
+    is_admin: bool,
+) -> Result<std::result::Result<bool, String>> {
+    let store_lock = get_combos_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let combos = match store.guilds.get_mut(guild_id.as_u64()) {
+        Some(combos) => combos,
+        None => return Ok(Ok(false)),
+    };
+    match combos.get(name) {
+        Some(combo) if combo.created_by != *user_id.as_u64() && !is_admin => {
+            return Ok(Err(not_creator(name)))
+        }
+        Some(_) => (),
+        None => return Ok(Ok(false)),
+    }
+    combos.remove(name);
+
+    persistence::save(COMBOS_STORE_NAME, &*store)?;
+
+    Ok(Ok(true))
+}
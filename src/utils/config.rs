@@ -1,3 +1,5 @@
+use std::{collections::HashMap, time::Duration};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -5,6 +7,20 @@ pub(crate) struct Config {
     pub discord_token: String,
     pub intros: IntroConfig,
     pub application_id: u64,
+    #[serde(default)]
+    pub random: RandomConfig,
+    #[serde(default)]
+    pub import: ImportConfig,
+    #[serde(default)]
+    pub urls: UrlConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub fades: FadeConfig,
+    #[serde(default)]
+    pub tts: TtsConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -18,3 +34,197 @@ pub struct UserIntro {
     pub user: u64,
     pub sound_file: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RandomConfig {
+    /// How many of a guild's most recent plays are excluded from the next random pick
+    pub avoid_repeats: usize,
+}
+
+impl Default for RandomConfig {
+    fn default() -> Self {
+        RandomConfig { avoid_repeats: 5 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ImportConfig {
+    /// youtube-dl compatible binary used to download imported sounds, e.g. `yt-dlp`
+    pub downloader: String,
+    /// Whether imported sounds get their loudness normalized
+    pub normalize: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UrlConfig {
+    /// Domains URLs may be played from, including their subdomains. Empty allows every domain.
+    pub allowed_domains: Vec<String>,
+    /// Domains URLs are never played from, even if they are allowed
+    pub denied_domains: Vec<String>,
+    /// Longest video or audio file in seconds that may be played, 0 for no limit
+    pub max_duration_secs: u64,
+    /// Whether URLs may point at Pascal's own machine or local network, e.g. a radio on the LAN.
+    /// Without it, URLs and the streams the downloader resolves them to are checked, but
+    /// redirects that ffmpeg or the downloader follow while fetching are not. Keep Pascal
+    /// firewalled off from anything sensitive on its network as well.
+    pub allow_private_hosts: bool,
+}
+
+impl UrlConfig {
+    pub fn max_duration(&self) -> Option<Duration> {
+        if self.max_duration_secs > 0 {
+            Some(Duration::from_secs(self.max_duration_secs))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for UrlConfig {
+    fn default() -> Self {
+        UrlConfig {
+            allowed_domains: Vec::new(),
+            denied_domains: Vec::new(),
+            max_duration_secs: 600,
+            allow_private_hosts: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Whether remote media is kept on disk after its first play
+    pub enabled: bool,
+    /// How long a download is reused before it's fetched again
+    pub ttl_hours: u64,
+    /// Disk space the cache may take up, least recently played media is evicted first
+    pub max_size_mb: u64,
+}
+
+impl CacheConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_hours * 60 * 60)
+    }
+
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb * 1024 * 1024
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: true,
+            ttl_hours: 7 * 24,
+            max_size_mb: 1024,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FadeConfig {
+    /// Milliseconds tracks take to fade out when they're stopped, skipped or cut off
+    pub fade_out_ms: u64,
+    /// Milliseconds individual sounds take to fade in, by sound name
+    pub fade_ins: HashMap<String, u64>,
+}
+
+impl FadeConfig {
+    pub fn fade_out(&self) -> Duration {
+        Duration::from_millis(self.fade_out_ms)
+    }
+
+    pub fn fade_in(&self, sound_name: &str) -> Option<Duration> {
+        self.fade_ins
+            .get(sound_name)
+            .filter(|ms| **ms > 0)
+            .map(|ms| Duration::from_millis(*ms))
+    }
+}
+
+impl Default for FadeConfig {
+    fn default() -> Self {
+        FadeConfig {
+            fade_out_ms: 1500,
+            fade_ins: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TtsEngine {
+    #[serde(rename = "espeak-ng")]
+    EspeakNg,
+    #[serde(rename = "piper")]
+    Piper,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TtsConfig {
+    /// Offline speech synthesizer to run
+    pub engine: TtsEngine,
+    /// Binary of the engine, if it isn't on the path under its usual name
+    pub binary: Option<String>,
+    /// espeak-ng voice like `en-us`, or the path of a piper voice model
+    pub voice: String,
+    /// Speaking rate, 1 being the voice's normal speed
+    pub speed: f32,
+    /// Longest text in characters that may be spoken
+    pub max_length: usize,
+    /// Whether users without an intro sound are greeted by speech instead
+    pub intro_fallback: bool,
+    /// Greeting spoken as the fallback intro, `{name}` is replaced by the user's nickname
+    pub intro_template: String,
+}
+
+impl TtsConfig {
+    pub fn binary(&self) -> &str {
+        match (&self.binary, self.engine) {
+            (Some(binary), _) => binary,
+            (None, TtsEngine::EspeakNg) => "espeak-ng",
+            (None, TtsEngine::Piper) => "piper",
+        }
+    }
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        TtsConfig {
+            engine: TtsEngine::EspeakNg,
+            binary: None,
+            voice: "en".to_string(),
+            speed: 1.0,
+            max_length: 200,
+            intro_fallback: false,
+            intro_template: "Welcome back, {name}".to_string(),
+        }
+    }
+}
+
+impl Default for ImportConfig {
+    fn default() -> Self {
+        ImportConfig {
+            downloader: "youtube-dl".to_string(),
+            normalize: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// Seconds of voice audio kept for clips in guilds that opted in
+    pub buffer_secs: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig { buffer_secs: 30 }
+    }
+}
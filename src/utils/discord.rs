@@ -1,4 +1,5 @@
 use log::error;
+use serenity::async_trait;
 use serenity::client::Context;
 
 use anyhow::Context as AnyhowCtx;
@@ -6,16 +7,85 @@ use anyhow::Result;
 use serenity::model::id::ChannelId;
 use serenity::model::id::GuildId;
 use serenity::model::id::UserId;
-use songbird::input;
+use songbird::create_player;
+use songbird::input::restartable::Restartable;
+use songbird::input::Input;
+use songbird::tracks::Track;
+use songbird::tracks::TrackHandle;
+use songbird::Event;
+use songbird::EventContext;
+use songbird::EventHandler as VoiceEventHandler;
+use songbird::TrackEvent;
 
 use crate::utils::error::check_msg;
 use crate::utils::error::handle_error;
 
+use super::combos::get_combo;
+use super::effects::filtered_source;
+use super::effects::probe_duration;
+use super::effects::AudioOptions;
+use super::guild_settings::get_duration_limits;
+use super::history::record_play;
+use super::history::PlayInterface;
+use super::history::PlayOrigin;
+use super::history::PlayedMedia;
+use super::idle::mark_active;
+use super::recording::start_recording;
+use super::sequence::play_sequence;
 use super::sound_files::get_sound_files;
 use super::sound_files::SoundFile;
+use super::stats::record_play_stat;
+use super::tracks::enforce_duration_limit;
+use super::tracks::fade_out_and_stop;
+use super::tracks::format_timestamp;
+use super::tracks::get_active_tracks;
+use super::tracks::get_current_track;
+use super::tracks::get_fade_in;
+use super::tracks::get_fade_out;
+use super::tracks::register_track;
+use super::tracks::start_fade_in;
+use super::url_source::classify_url;
+use super::url_source::url_source;
 
-pub async fn play_sound(ctx: &Context, guild_id: GuildId, sound_file: &SoundFile) -> Result<()> {
-    let src = input::ffmpeg(sound_file.file_path.clone())
+pub async fn play_sound(
+    ctx: &Context,
+    guild_id: GuildId,
+    sound_file: &SoundFile,
+    origin: PlayOrigin,
+    options: &AudioOptions,
+) -> Result<TrackHandle> {
+    play_file(ctx, guild_id, sound_file, origin, options, true).await
+}
+
+/// Plays synthesized speech. Unlike library sounds it is kept out of the history and stats,
+/// where it would show up as a sound that can't be played again.
+pub async fn play_speech(
+    ctx: &Context,
+    guild_id: GuildId,
+    sound_file: &SoundFile,
+    origin: PlayOrigin,
+) -> Result<TrackHandle> {
+    play_file(
+        ctx,
+        guild_id,
+        sound_file,
+        origin,
+        &AudioOptions::default(),
+        false,
+    )
+    .await
+}
+
+async fn play_file(
+    ctx: &Context,
+    guild_id: GuildId,
+    sound_file: &SoundFile,
+    origin: PlayOrigin,
+    options: &AudioOptions,
+    recorded: bool,
+) -> Result<TrackHandle> {
+    // Restartable sources can be seeked and looped
+    let src = filtered_source(&sound_file.file_path, options, false)
         .await
         .with_context(|| handle_error("Error reading ffmpeg source".to_string()))?;
 
@@ -28,10 +98,13 @@ pub async fn play_sound(ctx: &Context, guild_id: GuildId, sound_file: &SoundFile
         .get(guild_id)
         .ok_or_else(|| handle_error("Couldn't get handler lock".to_string()))?;
 
-    let mut handler = handler_lock.lock().await;
-    handler.play_source(src);
+    let media = PlayedMedia::Sound(sound_file.name.clone());
+    let (player, track) = create_track(ctx, src, &media).await;
+    handler_lock.lock().await.play(player);
 
-    Ok(())
+    track_started(ctx, guild_id, media, origin, track.clone(), recorded).await;
+
+    Ok(track)
 }
 
 pub async fn play_from_file(
@@ -39,39 +112,105 @@ pub async fn play_from_file(
     channel_id: ChannelId,
     guild_id: GuildId,
     file_name: &str,
+    origin: PlayOrigin,
+) -> Result<()> {
+    play_from_file_with_options(
+        ctx,
+        channel_id,
+        guild_id,
+        file_name,
+        origin,
+        &AudioOptions::default(),
+    )
+    .await
+}
+
+pub async fn play_from_file_with_options(
+    ctx: &Context,
+    channel_id: ChannelId,
+    guild_id: GuildId,
+    file_name: &str,
+    origin: PlayOrigin,
+    options: &AudioOptions,
 ) -> Result<()> {
     let sound_files = get_sound_files()?;
 
-    match sound_files.get(file_name) {
-        Some(file) => {
-            crate::utils::discord::play_sound(ctx, guild_id, file).await?;
-        }
-        None => {
-            // TODO: Refactor into error methods or smth
-            check_msg(
-                channel_id
-                    .say(
-                        &ctx.http,
-                        format!(
-                            "I don't know this sound: **{}**\nType `!list` to see a list of sounds",
-                            file_name
-                        ),
-                    )
-                    .await,
-            );
+    if let Some(file) = sound_files.get(file_name) {
+        if let Some(limit) = get_duration_limits(ctx, guild_id).await?.sound {
+            if let Some(Some(length)) = probe_duration(&file.file_path).await {
+                let length = options.played_length(length);
+                if length > limit {
+                    let message = format!(
+                        "**{}** is {} long, sounds may only play for {} here",
+                        file_name,
+                        format_timestamp(length),
+                        format_timestamp(limit)
+                    );
+                    check_msg(channel_id.say(&ctx.http, message).await);
+
+                    return Ok(());
+                }
+            }
         }
+
+        crate::utils::discord::play_sound(ctx, guild_id, file, origin, options).await?;
+        return Ok(());
+    }
+
+    // Saved combos can be played like any other sound
+    if let Some(combo) = get_combo(ctx, guild_id, file_name).await? {
+        let steps = match combo.steps() {
+            Ok(steps) => steps,
+            Err(reason) => {
+                check_msg(channel_id.say(&ctx.http, reason).await);
+                return Ok(());
+            }
+        };
+        return play_sequence(
+            ctx,
+            channel_id,
+            guild_id,
+            steps,
+            combo.gap(),
+            origin,
+            options.clone(),
+        )
+        .await;
     }
 
+    // TODO: Refactor into error methods or smth
+    check_msg(
+        channel_id
+            .say(
+                &ctx.http,
+                format!(
+                    "I don't know this sound: **{}**\nType `!list` to see a list of sounds",
+                    file_name
+                ),
+            )
+            .await,
+    );
+
     Ok(())
 }
 
-// TODO: Refactor like play_sound, i.e. more specialized and modular
-pub async fn play_youtube(
+/// Plays a URL after checking it against the configured domain lists and duration limit
+pub async fn play_url(
     ctx: &Context,
     channel_id: ChannelId,
     guild_id: GuildId,
     url: &str,
+    origin: PlayOrigin,
+    options: &AudioOptions,
 ) -> Result<()> {
+    let kind = match classify_url(ctx, url, Some(guild_id)).await? {
+        Ok(kind) => kind,
+        Err(reason) => {
+            check_msg(channel_id.say(&ctx.http, reason).await);
+            return Ok(());
+        }
+    };
+
     let manager = songbird::get(ctx)
         .await
         .ok_or_else(|| handle_error("Songbird client not initialized".to_string()))?;
@@ -80,21 +219,264 @@ pub async fn play_youtube(
         .get(guild_id)
         .ok_or_else(|| handle_error("Couldn't get handler lock".to_string()))?;
 
-    let mut handler = handler_lock.lock().await;
-
-    match songbird::ytdl(&url).await {
-        Ok(source) => handler.play_only_source(source),
+    let track = match url_source(ctx, url, kind, options, false).await {
+        Ok(source) => handler_lock.lock().await.play_only_source(source),
         Err(err) => {
-            let err_message = format!("Error streaming youtube source: {}", err);
+            let err_message = format!("Error streaming source: {}", err);
             check_msg(channel_id.say(&ctx.http, err_message.clone()).await);
 
             return Err(handle_error(err_message));
         }
     };
 
+    track_started(
+        ctx,
+        guild_id,
+        PlayedMedia::Url(url.to_owned()),
+        origin,
+        track,
+        true,
+    )
+    .await;
+
     Ok(())
 }
 
+/// Adds a library sound to the end of the guild's queue
+pub async fn enqueue_sound(
+    ctx: &Context,
+    guild_id: GuildId,
+    sound_file: &SoundFile,
+    origin: PlayOrigin,
+) -> Result<TrackHandle> {
+    let src = Restartable::ffmpeg(sound_file.file_path.clone(), true)
+        .await
+        .with_context(|| handle_error("Error reading ffmpeg source".to_string()))?;
+
+    enqueue(
+        ctx,
+        guild_id,
+        src.into(),
+        PlayedMedia::Sound(sound_file.name.clone()),
+        origin,
+    )
+    .await
+}
+
+/// Adds a URL to the end of the guild's queue.
+/// The source is only fetched once the queue reaches it.
+pub async fn enqueue_url(
+    ctx: &Context,
+    guild_id: GuildId,
+    url: &str,
+    origin: PlayOrigin,
+) -> Result<TrackHandle> {
+    let kind = classify_url(ctx, url, Some(guild_id))
+        .await?
+        .map_err(handle_error)?;
+    let src = url_source(ctx, url, kind, &AudioOptions::default(), true)
+        .await
+        .with_context(|| handle_error("Error streaming URL source".to_string()))?;
+
+    enqueue(ctx, guild_id, src, PlayedMedia::Url(url.to_owned()), origin).await
+}
+
+async fn enqueue(
+    ctx: &Context,
+    guild_id: GuildId,
+    src: Input,
+    media: PlayedMedia,
+    origin: PlayOrigin,
+) -> Result<TrackHandle> {
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or_else(|| handle_error("Songbird Voice client not initialized".to_string()))?;
+
+    let handler_lock = manager
+        .get(guild_id)
+        .ok_or_else(|| handle_error("Couldn't get handler lock".to_string()))?;
+
+    let (player, track) = create_track(ctx, src, &media).await;
+    let starts_now = {
+        let mut handler = handler_lock.lock().await;
+        let starts_now = handler.queue().is_empty();
+        handler.enqueue(player);
+        starts_now
+    };
+
+    // Queued tracks wait paused, so their start shows up as a play event.
+    // The head of the queue starts right away and never fires one.
+    if starts_now {
+        track_started(ctx, guild_id, media, origin, track.clone(), true).await;
+    } else {
+        let recorder = TrackStartRecorder {
+            ctx: ctx.clone(),
+            guild_id,
+            media,
+            origin,
+        };
+        track
+            .add_event(Event::Track(TrackEvent::Play), recorder)
+            .with_context(|| handle_error("Error watching queued track".to_string()))?;
+    }
+
+    Ok(track)
+}
+
+struct TrackStartRecorder {
+    ctx: Context,
+    guild_id: GuildId,
+    media: PlayedMedia,
+    origin: PlayOrigin,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackStartRecorder {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            if let Some((_, track)) = tracks.first() {
+                track_started(
+                    &self.ctx,
+                    self.guild_id,
+                    self.media.clone(),
+                    self.origin,
+                    (*track).clone(),
+                    true,
+                )
+                .await;
+            }
+        }
+
+        // A resumed track must not be recorded twice
+        Some(Event::Cancel)
+    }
+}
+
+/// Wraps the source in a track, muted if the sound fades in once it starts
+async fn create_track(ctx: &Context, src: Input, media: &PlayedMedia) -> (Track, TrackHandle) {
+    let (mut player, track) = create_player(src);
+    if let PlayedMedia::Sound(name) = media {
+        if get_fade_in(ctx, name).await.is_some() {
+            player.set_volume(0.0);
+        }
+    }
+
+    (player, track)
+}
+
+/// Fades out everything the guild is playing and clears its queue
+pub async fn stop_playback(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or_else(|| handle_error("Songbird Voice client not initialized".to_string()))?;
+
+    let handler_lock = manager
+        .get(guild_id)
+        .ok_or_else(|| handle_error("Couldn't get handler lock".to_string()))?;
+
+    // Queued tracks must not start while the current ones fade out
+    let pending = handler_lock.lock().await.queue().modify_queue(|queue| {
+        if queue.len() > 1 {
+            queue.drain(1..).collect()
+        } else {
+            Vec::new()
+        }
+    });
+    for queued in pending {
+        if let Err(err) = queued.handle().stop() {
+            error!("Error stopping queued track: {}", err);
+        }
+    }
+
+    let fade = get_fade_out(ctx).await;
+    let fades: Vec<_> = get_active_tracks(ctx, guild_id)
+        .await?
+        .into_iter()
+        .map(|track| tokio::spawn(async move { fade_out_and_stop(&track, fade).await }))
+        .collect();
+    for fading in fades {
+        if let Err(err) = fading.await {
+            error!("Error fading out track: {}", err);
+        }
+    }
+
+    handler_lock.lock().await.stop();
+
+    Ok(())
+}
+
+/// Fades out the current track, which lets the queue move on to the next one.
+/// Returns false if nothing is playing.
+pub async fn skip_track(ctx: &Context, guild_id: GuildId) -> Result<bool> {
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or_else(|| handle_error("Songbird Voice client not initialized".to_string()))?;
+
+    let handler_lock = manager
+        .get(guild_id)
+        .ok_or_else(|| handle_error("Couldn't get handler lock".to_string()))?;
+
+    let queued = handler_lock.lock().await.queue().current();
+    let track = match queued {
+        Some(track) => Some(track),
+        None => get_current_track(ctx, guild_id).await?,
+    };
+
+    match track {
+        Some(track) => {
+            let fade = get_fade_out(ctx).await;
+            tokio::spawn(async move { fade_out_and_stop(&track, fade).await });
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Single place every started playback passes through, feeding history and statistics
+/// and cutting off tracks that run longer than the guild allows
+async fn track_started(
+    ctx: &Context,
+    guild_id: GuildId,
+    media: PlayedMedia,
+    origin: PlayOrigin,
+    track: TrackHandle,
+    recorded: bool,
+) {
+    if let PlayedMedia::Sound(name) = &media {
+        if let Some(fade) = get_fade_in(ctx, name).await {
+            start_fade_in(track.clone(), fade);
+        }
+    }
+
+    match get_duration_limits(ctx, guild_id).await {
+        Ok(limits) => {
+            let limit = match media {
+                PlayedMedia::Sound(_) => limits.sound,
+                PlayedMedia::Url(_) => limits.url,
+            };
+            if let Some(limit) = limit {
+                let fade = get_fade_out(ctx).await;
+                if let Err(err) = enforce_duration_limit(&track, limit, fade) {
+                    error!("{}", err);
+                }
+            }
+        }
+        Err(err) => error!("Could not get duration limits: {}", err),
+    }
+
+    mark_active(ctx, guild_id).await;
+    if let Err(err) = register_track(ctx, guild_id, track.clone()).await {
+        error!("Could not register track: {}", err);
+    }
+
+    // `/who` and `/history` would give the answer away, and guessing isn't picking a sound
+    if !recorded || origin.interface == PlayInterface::Quiz {
+        return;
+    }
+    record_play_stat(ctx, guild_id, &media, origin).await;
+    record_play(ctx, guild_id, media, origin, track).await;
+}
+
 pub async fn join_channel(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Result<()> {
     let manager = songbird::get(ctx)
         .await
@@ -105,6 +487,13 @@ pub async fn join_channel(ctx: &Context, guild_id: GuildId, channel_id: ChannelI
 
     join.1?;
 
+    // Joining counts as activity, so Pascal doesn't leave before it gets to play anything
+    mark_active(ctx, guild_id).await;
+
+    if let Err(err) = start_recording(ctx, guild_id, channel_id).await {
+        error!("Could not start recording in guild {}: {}", guild_id, err);
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,610 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    path::Path,
+    process::{Command as StdCommand, Stdio},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::warn;
+use serenity::async_trait;
+use songbird::input::{
+    self,
+    codec::Codec,
+    error::Result as InputResult,
+    restartable::{Restart, Restartable},
+    Container, Input, Metadata,
+};
+use tokio::{process::Command, time::timeout};
+
+use super::{error::handle_error, tracks::parse_timestamp};
+
+const SAMPLE_RATE: u32 = 48000;
+// Older ffmpeg versions only accept atempo factors within these bounds
+const MIN_FACTOR: f64 = 0.5;
+const MAX_FACTOR: f64 = 2.0;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+// How much faster the chipmunk and slow effects play
+const CHIPMUNK_TEMPO: f64 = 1.5;
+const SLOW_TEMPO: f64 = 0.75;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    Chipmunk,
+    Slow,
+    Reverse,
+    BassBoost,
+    Echo,
+    Robot,
+}
+
+pub const EFFECTS: [Effect; 6] = [
+    Effect::Chipmunk,
+    Effect::Slow,
+    Effect::Reverse,
+    Effect::BassBoost,
+    Effect::Echo,
+    Effect::Robot,
+];
+
+impl Effect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::Chipmunk => "chipmunk",
+            Effect::Slow => "slow",
+            Effect::Reverse => "reverse",
+            Effect::BassBoost => "bassboost",
+            Effect::Echo => "echo",
+            Effect::Robot => "robot",
+        }
+    }
+
+    fn filter(&self) -> String {
+        match self {
+            Effect::Chipmunk => format!(
+                "asetrate={}*{},aresample={}",
+                SAMPLE_RATE, CHIPMUNK_TEMPO, SAMPLE_RATE
+            ),
+            Effect::Slow => format!("atempo={}", SLOW_TEMPO),
+            Effect::Reverse => "areverse".to_string(),
+            Effect::BassBoost => "bass=g=15".to_string(),
+            Effect::Echo => "aecho=0.8:0.88:60:0.4".to_string(),
+            Effect::Robot => "afftfilt=real='hypot(re,im)*sin(0)':imag='hypot(re,im)*cos(0)':win_size=512:overlap=0.75".to_string(),
+        }
+    }
+
+    /// How much faster the effect makes a sound play
+    fn tempo(&self) -> f64 {
+        match self {
+            Effect::Chipmunk => CHIPMUNK_TEMPO,
+            Effect::Slow => SLOW_TEMPO,
+            _ => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        EFFECTS
+            .iter()
+            .find(|effect| effect.name().eq_ignore_ascii_case(name))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = EFFECTS.iter().map(|effect| effect.name()).collect();
+                format!(
+                    "Unknown effect **{}**, try one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// How a sound should be altered when it's played
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioOptions {
+    pub effects: Vec<Effect>,
+    pub pitch: Option<f64>,
+    pub speed: Option<f64>,
+    /// Offset into the source to start playing at
+    pub start: Option<Duration>,
+    /// Offset into the source to stop playing at
+    pub end: Option<Duration>,
+    /// Evens out the loudness, mostly useful for imported sounds
+    pub normalize: bool,
+}
+
+impl AudioOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == AudioOptions::default()
+    }
+
+    pub fn add_effect(&mut self, effect: Effect) {
+        if !self.effects.contains(&effect) {
+            self.effects.push(effect);
+        }
+    }
+
+    /// Takes over everything set in the other options
+    pub fn merge(&mut self, other: AudioOptions) {
+        other
+            .effects
+            .into_iter()
+            .for_each(|effect| self.add_effect(effect));
+        self.pitch = other.pitch.or(self.pitch);
+        self.speed = other.speed.or(self.speed);
+        self.start = other.start.or(self.start);
+        self.end = other.end.or(self.end);
+        self.normalize |= other.normalize;
+    }
+
+    /// Sets the trim offsets, making sure the end comes after the start
+    pub fn set_trim(
+        &mut self,
+        start: Option<Duration>,
+        end: Option<Duration>,
+    ) -> Result<(), String> {
+        if let (Some(start), Some(end)) = (start.or(self.start), end.or(self.end)) {
+            if end <= start {
+                return Err("The end has to come after the start".to_string());
+            }
+        }
+        self.start = start.or(self.start);
+        self.end = end.or(self.end);
+
+        Ok(())
+    }
+
+    /// How much faster the speed and effects make a sound play, combined
+    fn tempo(&self) -> f64 {
+        self.effects.iter().map(Effect::tempo).product::<f64>() * self.speed.unwrap_or(1.0)
+    }
+
+    /// How long a source of the given length plays with the trim, speed and effects applied
+    pub fn played_length(&self, source_length: Duration) -> Duration {
+        let end = self.end.map_or(source_length, |end| end.min(source_length));
+        let length = end
+            .checked_sub(self.start.unwrap_or_default())
+            .unwrap_or_default();
+
+        length.div_f64(self.tempo())
+    }
+
+    /// Returns the ffmpeg arguments going before and after the input for the options.
+    /// `seek` is an additional offset into the trimmed clip, e.g. when the track gets seeked.
+    fn ffmpeg_args(&self, seek: Option<Duration>) -> (Vec<String>, Vec<String>) {
+        let mut pre_input_args = Vec::new();
+        let mut args = Vec::new();
+
+        let start = self.start.unwrap_or_default() + seek.unwrap_or_default();
+        if !start.is_zero() {
+            pre_input_args.push("-ss".to_string());
+            pre_input_args.push(format!("{:.3}", start.as_secs_f64()));
+        }
+        if let Some(end) = self.end {
+            // With -ss in front of the input, output timestamps start at zero
+            let length = end.checked_sub(start).unwrap_or_default();
+            args.push("-to".to_string());
+            args.push(format!("{:.3}", length.as_secs_f64()));
+        }
+        if let Some(filter_graph) = self.filter_graph() {
+            args.push("-af".to_string());
+            args.push(filter_graph);
+        }
+
+        (pre_input_args, args)
+    }
+
+    /// Builds the ffmpeg filter graph for the options, if any apply
+    pub fn filter_graph(&self) -> Option<String> {
+        // Normalise the sample rate so rate based filters behave the same for every file
+        let mut filters = vec![format!("aresample={}", SAMPLE_RATE)];
+
+        if let Some(pitch) = self.pitch {
+            // Shift the pitch without changing the speed
+            filters.push(format!(
+                "asetrate={}*{},aresample={},atempo={}",
+                SAMPLE_RATE,
+                pitch,
+                SAMPLE_RATE,
+                1.0 / pitch
+            ));
+        }
+        if let Some(speed) = self.speed {
+            filters.push(format!("atempo={}", speed));
+        }
+        filters.extend(self.effects.iter().map(|effect| effect.filter()));
+        if self.normalize {
+            filters.push("loudnorm=I=-16:TP=-1.5:LRA=11".to_string());
+        }
+
+        if filters.len() > 1 {
+            Some(filters.join(","))
+        } else {
+            None
+        }
+    }
+
+    /// Parses `--flag [value]` style options.
+    /// Supported are `--pitch <factor>`, `--speed <factor>`, `--start <position>`,
+    /// `--end <position>`, `--effect <name>` and `--<effect name>`.
+    pub fn parse_flags(flags: &str) -> Result<Self, String> {
+        let mut options = AudioOptions::default();
+        let mut tokens = flags.split_whitespace();
+
+        while let Some(token) = tokens.next() {
+            let flag = token
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Expected a flag like `--echo`, got `{}`", token))?;
+
+            match flag {
+                "pitch" => options.pitch = Some(parse_factor("pitch", tokens.next())?),
+                "speed" => options.speed = Some(parse_factor("speed", tokens.next())?),
+                "start" => {
+                    let start = parse_position("start", tokens.next())?;
+                    options.set_trim(Some(start), None)?;
+                }
+                "end" => {
+                    let end = parse_position("end", tokens.next())?;
+                    options.set_trim(None, Some(end))?;
+                }
+                "effect" => {
+                    let name = tokens.next().ok_or("`--effect` needs an effect name")?;
+                    options.add_effect(name.parse()?);
+                }
+                name => options.add_effect(name.parse()?),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Parses a pitch or speed factor, which ffmpeg only accepts within certain bounds
+pub fn parse_factor(name: &str, value: Option<&str>) -> Result<f64, String> {
+    let factor = value
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|factor| factor.is_finite())
+        .ok_or_else(|| format!("`{}` needs a number like 1.5", name))?;
+
+    if !(MIN_FACTOR..=MAX_FACTOR).contains(&factor) {
+        return Err(format!(
+            "`{}` must be between {} and {}",
+            name, MIN_FACTOR, MAX_FACTOR
+        ));
+    }
+
+    Ok(factor)
+}
+
+/// Parses a trim offset like `1:23` or `4.5`
+pub fn parse_position(name: &str, value: Option<&str>) -> Result<Duration, String> {
+    value
+        .and_then(parse_timestamp)
+        .ok_or_else(|| format!("`{}` needs a position like 1:23 or 4.5", name))
+}
+
+/// Splits a text command argument like `bruh --pitch 1.5` into the sound and its options
+pub fn split_flags(argument: &str) -> (&str, &str) {
+    match argument.find(" --") {
+        Some(index) => (argument[..index].trim(), argument[index..].trim()),
+        None if argument.starts_with("--") => ("", argument),
+        None => (argument.trim(), ""),
+    }
+}
+
+/// Creates a seekable ffmpeg source for a file or stream URL with the options applied.
+/// Lazy sources only start ffmpeg once they are played.
+pub async fn filtered_source<S: AsRef<OsStr>>(
+    source: S,
+    options: &AudioOptions,
+    lazy: bool,
+) -> Result<Input> {
+    let restarter = FilteredFfmpeg {
+        source: source.as_ref().to_owned(),
+        options: options.clone(),
+    };
+
+    Ok(Restartable::new(restarter, lazy).await?.into())
+}
+
+/// Asks the youtube-dl compatible downloader for the direct audio stream behind a page URL
+/// so ffmpeg can read it
+pub async fn resolve_stream_url(downloader: &str, url: &str) -> Result<String> {
+    let output = Command::new(downloader)
+        .args(&["-f", "bestaudio/best", "-g", "--no-playlist", url])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("Error running {}", downloader))?;
+
+    if !output.status.success() {
+        return Err(handle_error(format!(
+            "{} could not resolve {}: {}",
+            downloader,
+            url,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_owned())
+        .filter(|line| !line.is_empty())
+        .ok_or_else(|| handle_error(format!("{} found no stream for {}", downloader, url)))
+}
+
+/// Asks ffprobe for the duration of a file or the media behind a URL.
+/// Returns None if ffprobe can't read it, e.g. because it's a web page,
+/// and `Some(None)` if the media has no duration.
+pub async fn probe_duration<S: AsRef<OsStr>>(source: S) -> Option<Option<Duration>> {
+    let probe = Command::new("ffprobe")
+        .args(&[
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(source.as_ref())
+        .kill_on_drop(true)
+        .output();
+
+    let output = match timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok(output)) if output.status.success() => output,
+        Ok(Ok(_)) => return None,
+        Ok(Err(err)) => {
+            warn!("Error running ffprobe: {}", err);
+            return None;
+        }
+        Err(_) => {
+            warn!("Probing {:?} timed out", source.as_ref());
+            return None;
+        }
+    };
+
+    let duration = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(Duration::from_secs_f64);
+
+    Some(duration)
+}
+
+/// Writes the source with the options applied into a new audio file
+pub async fn render_to_file<S: AsRef<OsStr>>(
+    source: S,
+    options: &AudioOptions,
+    destination: &Path,
+) -> Result<()> {
+    let (pre_input_args, args) = options.ffmpeg_args(None);
+
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .args(&pre_input_args)
+        .arg("-i")
+        .arg(source.as_ref())
+        .args(&args)
+        .arg("-vn")
+        .arg(destination)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| "Error running ffmpeg")?;
+
+    if !output.status.success() {
+        return Err(handle_error(format!(
+            "ffmpeg failed to render {:?}: {}",
+            destination,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
+struct FilteredFfmpeg {
+    source: OsString,
+    options: AudioOptions,
+}
+
+#[async_trait]
+impl Restart for FilteredFfmpeg {
+    async fn call_restart(&mut self, time: Option<Duration>) -> InputResult<Input> {
+        // Songbird seeks in the played sound, which runs faster or slower than the source
+        let seek = time.map(|time| time.mul_f64(self.options.tempo()));
+        let (mut pre_input_args, mut args) = self.options.ffmpeg_args(seek);
+        // Radio streams and remote files drop out now and then
+        if self.source.to_string_lossy().starts_with("http") {
+            pre_input_args.extend(
+                [
+                    "-reconnect",
+                    "1",
+                    "-reconnect_streamed",
+                    "1",
+                    "-reconnect_delay_max",
+                    "5",
+                ]
+                .iter()
+                .map(|arg| arg.to_string()),
+            );
+        }
+        // Songbird expects raw 48kHz stereo float samples
+        args.extend(
+            [
+                "-f",
+                "s16le",
+                "-ac",
+                "2",
+                "-ar",
+                "48000",
+                "-acodec",
+                "pcm_f32le",
+                "-",
+            ]
+            .iter()
+            .map(|arg| arg.to_string()),
+        );
+
+        let duration = match probe_duration(&self.source).await {
+            Some(Some(length)) => Some(self.options.played_length(length)),
+            _ => None,
+        };
+
+        let ffmpeg = StdCommand::new("ffmpeg")
+            .args(&pre_input_args)
+            .arg("-i")
+            .arg(&self.source)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        // ffmpeg always puts out stereo, even for mono sources, so the input is never
+        // probed for its channels like `ffmpeg_optioned` would
+        Ok(Input::new(
+            true,
+            input::children_to_reader::<f32>(vec![ffmpeg]),
+            Codec::FloatPcm,
+            Container::Raw,
+            Some(Metadata {
+                duration,
+                channels: Some(2),
+                sample_rate: Some(SAMPLE_RATE),
+                ..Default::default()
+            }),
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> InputResult<(Option<Metadata>, Codec, Container)> {
+        Ok((None, Codec::FloatPcm, Container::Raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flags() {
+        let options =
+            AudioOptions::parse_flags("--pitch 1.5 --echo --effect ROBOT --start 0:12").unwrap();
+
+        assert_eq!(options.pitch, Some(1.5));
+        assert_eq!(options.effects, vec![Effect::Echo, Effect::Robot]);
+        assert_eq!(options.start, Some(Duration::from_secs(12)));
+        assert_eq!(options.end, None);
+    }
+
+    #[test]
+    fn rejects_invalid_flags() {
+        assert!(AudioOptions::parse_flags("echo").is_err());
+        assert!(AudioOptions::parse_flags("--wobble").is_err());
+        assert!(AudioOptions::parse_flags("--effect").is_err());
+        assert!(AudioOptions::parse_flags("--pitch").is_err());
+        assert!(AudioOptions::parse_flags("--pitch fast").is_err());
+        assert!(AudioOptions::parse_flags("--speed 3").is_err());
+        assert!(AudioOptions::parse_flags("--speed NaN").is_err());
+        assert!(AudioOptions::parse_flags("--start 0:20 --end 0:10").is_err());
+        assert!(AudioOptions::parse_flags("--end 1e300").is_err());
+    }
+
+    #[test]
+    fn adds_effects_only_once() {
+        let options = AudioOptions::parse_flags("--echo --echo").unwrap();
+
+        assert_eq!(options.effects, vec![Effect::Echo]);
+    }
+
+    #[test]
+    fn builds_no_filter_graph_without_options() {
+        assert_eq!(AudioOptions::default().filter_graph(), None);
+    }
+
+    #[test]
+    fn builds_filter_graph_in_order() {
+        let options = AudioOptions::parse_flags("--speed 2 --reverse").unwrap();
+
+        assert_eq!(
+            options.filter_graph(),
+            Some("aresample=48000,atempo=2,areverse".to_string())
+        );
+    }
+
+    #[test]
+    fn shifts_pitch_without_changing_speed() {
+        let options = AudioOptions::parse_flags("--pitch 2").unwrap();
+
+        assert_eq!(
+            options.filter_graph(),
+            Some("aresample=48000,asetrate=48000*2,aresample=48000,atempo=0.5".to_string())
+        );
+    }
+
+    #[test]
+    fn computes_played_length() {
+        let options = AudioOptions::parse_flags("--start 10 --end 40 --speed 2").unwrap();
+
+        assert_eq!(
+            options.played_length(Duration::from_secs(60)),
+            Duration::from_secs(15)
+        );
+        assert_eq!(
+            options.played_length(Duration::from_secs(5)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn computes_played_length_with_effects() {
+        let options = AudioOptions::parse_flags("--slow").unwrap();
+        assert_eq!(
+            options.played_length(Duration::from_secs(30)),
+            Duration::from_secs(40)
+        );
+
+        let options = AudioOptions::parse_flags("--chipmunk --echo").unwrap();
+        assert_eq!(
+            options.played_length(Duration::from_secs(30)),
+            Duration::from_secs(20)
+        );
+
+        let options = AudioOptions::parse_flags("--speed 1.5 --slow --pitch 2").unwrap();
+        assert_eq!(
+            options.played_length(Duration::from_secs(9)),
+            Duration::from_secs(8)
+        );
+    }
+
+    #[test]
+    fn builds_effect_filters() {
+        let options = AudioOptions::parse_flags("--chipmunk --slow").unwrap();
+
+        assert_eq!(
+            options.filter_graph(),
+            Some("aresample=48000,asetrate=48000*1.5,aresample=48000,atempo=0.75".to_string())
+        );
+    }
+
+    #[test]
+    fn splits_flags_from_sound() {
+        assert_eq!(split_flags("bruh --pitch 1.5"), ("bruh", "--pitch 1.5"));
+        assert_eq!(split_flags("--echo"), ("", "--echo"));
+        assert_eq!(split_flags("bruh"), ("bruh", ""));
+    }
+}
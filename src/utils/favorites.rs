@@ -0,0 +1,110 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::UserId,
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{error::handle_error, persistence};
+
+pub const FAVORITES_STORE_NAME: &str = "favorites";
+pub const MAX_FAVORITES: usize = 25;
+
+pub struct FavoritesStore;
+
+impl TypeMapKey for FavoritesStore {
+    type Value = Arc<Mutex<Favorites>>;
+}
+
+/// Each user's favorite sounds. The position of a favorite is its quick slot, starting at 1.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Favorites {
+    pub users: HashMap<u64, Vec<String>>,
+}
+
+pub enum AddFavoriteResult {
+    Added(usize),
+    AlreadyFavorite(usize),
+    Full,
+}
+
+async fn get_favorites_store(ctx: &Context) -> Result<Arc<Mutex<Favorites>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<FavoritesStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get favorites store".to_string()))
+}
+
+pub async fn get_favorites(ctx: &Context, user_id: UserId) -> Result<Vec<String>> {
+    let store_lock = get_favorites_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store
+        .users
+        .get(user_id.as_u64())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Returns the sound in the user's quick slot, if that slot is taken
+pub async fn get_quick_slot(ctx: &Context, user_id: UserId, slot: usize) -> Result<Option<String>> {
+    let favorites = get_favorites(ctx, user_id).await?;
+
+    Ok(slot
+        .checked_sub(1)
+        .and_then(|index| favorites.get(index))
+        .cloned())
+}
+
+/// Adds a sound to the end of the user's favorites, i.e. into the next free quick slot
+pub async fn add_favorite(
+    ctx: &Context,
+    user_id: UserId,
+    sound: &str,
+) -> Result<AddFavoriteResult> {
+    let store_lock = get_favorites_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+    let favorites = store.users.entry(*user_id.as_u64()).or_default();
+
+    if let Some(index) = favorites.iter().position(|favorite| favorite == sound) {
+        return Ok(AddFavoriteResult::AlreadyFavorite(index + 1));
+    }
+    if favorites.len() >= MAX_FAVORITES {
+        return Ok(AddFavoriteResult::Full);
+    }
+
+    favorites.push(sound.to_owned());
+    let slot = favorites.len();
+    persistence::save(FAVORITES_STORE_NAME, &*store)?;
+
+    Ok(AddFavoriteResult::Added(slot))
+}
+
+/// Removes a sound from the user's favorites. Later favorites move up one quick slot.
+/// Returns false if the sound wasn't a favorite.
+pub async fn remove_favorite(ctx: &Context, user_id: UserId, sound: &str) -> Result<bool> {
+    let store_lock = get_favorites_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let favorites = match store.users.get_mut(user_id.as_u64()) {
+        Some(favorites) => favorites,
+        None => return Ok(false),
+    };
+    let index = match favorites.iter().position(|favorite| favorite == sound) {
+        Some(index) => index,
+        None => return Ok(false),
+    };
+
+    favorites.remove(index);
+    if favorites.is_empty() {
+        store.users.remove(user_id.as_u64());
+    }
+    persistence::save(FAVORITES_STORE_NAME, &*store)?;
+
+    Ok(true)
+}
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use log::{error, info};
+use serenity::{
+    client::Context,
+    model::{
+        id::{GuildId, UserId},
+        prelude::VoiceState,
+    },
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{discord::join_channel, error::handle_error, idle::get_connected_channel};
+
+/// The user Pascal follows between voice channels in each guild
+pub struct FollowStore;
+
+impl TypeMapKey for FollowStore {
+    type Value = Arc<Mutex<HashMap<GuildId, UserId>>>;
+}
+
+async fn get_follow_store(ctx: &Context) -> Result<Arc<Mutex<HashMap<GuildId, UserId>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<FollowStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get follow store".to_string()))
+}
+
+/// Returns the user Pascal follows in the guild, if any
+pub async fn get_followed_user(ctx: &Context, guild_id: GuildId) -> Result<Option<UserId>> {
+    let store_lock = get_follow_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store.get(&guild_id).copied())
+}
+
+/// Makes Pascal follow the user, replacing whoever it followed before
+pub async fn follow_user(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Result<()> {
+    let store_lock = get_follow_store(ctx).await?;
+    store_lock.lock().await.insert(guild_id, user_id);
+
+    Ok(())
+}
+
+/// Stops following anyone in the guild, returning who was followed
+pub async fn unfollow_user(ctx: &Context, guild_id: GuildId) -> Result<Option<UserId>> {
+    let store_lock = get_follow_store(ctx).await?;
+    let previous = store_lock.lock().await.remove(&guild_id);
+
+    Ok(previous)
+}
+
+/// Moves Pascal to the channel the followed user switched to
+pub async fn follow_voice_state(ctx: &Context, guild_id: GuildId, new_state: &VoiceState) {
+    let followed = match get_followed_user(ctx, guild_id).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return,
+        Err(err) => {
+            error!("{}", err);
+            return;
+        }
+    };
+    if followed != new_state.user_id {
+        return;
+    }
+
+    // When the followed user disconnects Pascal stays put until they come back
+    let channel_id = match new_state.channel_id {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    if get_connected_channel(ctx, guild_id).await == Some(channel_id) {
+        return;
+    }
+
+    info!(
+        "Following {} to {} in guild {}",
+        followed, channel_id, guild_id
+    );
+    if let Err(err) = join_channel(ctx, guild_id, channel_id).await {
+        error!("Error following user to voice channel: {}", err);
+    }
+}
@@ -44,3 +44,45 @@ pub fn get_lookup_results(search_key: &str, elements: Vec<String>) -> Vec<String
 
     results
 }
+
+/// Orders elements by how often they were used, most used first, for when there is
+/// nothing to search for yet. Ties are broken alphabetically.
+pub fn get_ranked_results(mut elements: Vec<String>, usage: &HashMap<String, i64>) -> Vec<String> {
+    elements.sort_unstable_by(|a, b| {
+        let uses_a = usage.get(a).copied().unwrap_or_default();
+        let uses_b = usage.get(b).copied().unwrap_or_default();
+
+        uses_b.cmp(&uses_a).then_with(|| a.cmp(b))
+    });
+    elements.truncate(MAX_AUTOCOMPLETE_RESULTS);
+
+    elements
+}
+
+/// Puts the given favorites in front of the results, dropping duplicates
+pub fn prepend_favorites(favorites: Vec<String>, results: Vec<String>) -> Vec<String> {
+    let mut combined = favorites;
+    for result in results {
+        if !combined.contains(&result) {
+            combined.push(result);
+        }
+    }
+    combined.truncate(MAX_AUTOCOMPLETE_RESULTS);
+
+    combined
+}
+
+/// Returns the element the search key matches best, if it matches any at all
+pub fn get_best_match(search_key: &str, elements: &[String]) -> Option<String> {
+    let matcher = SkimMatcherV2::default();
+
+    elements
+        .iter()
+        .filter_map(|element| {
+            matcher
+                .fuzzy_match(element, search_key)
+                .map(|score| (score, element))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, element)| element.clone())
+}
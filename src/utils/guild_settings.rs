@@ -0,0 +1,113 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::GuildId,
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{error::handle_error, persistence, url_source::get_url_config};
+
+pub const GUILD_SETTINGS_STORE_NAME: &str = "guild_settings";
+
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+pub struct GuildSettingsStore;
+
+impl TypeMapKey for GuildSettingsStore {
+    type Value = Arc<Mutex<GuildSettings>>;
+}
+
+/// Settings guild admins can change at runtime
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GuildSettings {
+    pub guilds: HashMap<u64, GuildSettingsEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct GuildSettingsEntry {
+    /// Longest library sound in seconds, 0 for no limit
+    pub max_sound_duration_secs: Option<u64>,
+    /// Longest URL in seconds, 0 for no limit. Falls back to the configured limit.
+    pub max_url_duration_secs: Option<u64>,
+    /// Minutes without playback after which Pascal leaves the voice channel, 0 to stay
+    pub idle_timeout_mins: Option<u64>,
+    /// Text channel voice recording is announced in. Nothing is recorded while unset.
+    pub recording_channel: Option<u64>,
+}
+
+/// How long sounds and URLs may play in a guild
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DurationLimits {
+    pub sound: Option<Duration>,
+    pub url: Option<Duration>,
+}
+
+fn to_limit(secs: u64) -> Option<Duration> {
+    if secs > 0 {
+        Some(Duration::from_secs(secs))
+    } else {
+        None
+    }
+}
+
+async fn get_settings_store(ctx: &Context) -> Result<Arc<Mutex<GuildSettings>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<GuildSettingsStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get guild settings store".to_string()))
+}
+
+pub async fn get_guild_settings(ctx: &Context, guild_id: GuildId) -> Result<GuildSettingsEntry> {
+    let store_lock = get_settings_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store
+        .guilds
+        .get(guild_id.as_u64())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Applies a change to the guild's settings and persists them
+pub async fn update_guild_settings<F: FnOnce(&mut GuildSettingsEntry)>(
+    ctx: &Context,
+    guild_id: GuildId,
+    update: F,
+) -> Result<()> {
+    let store_lock = get_settings_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    update(store.guilds.entry(*guild_id.as_u64()).or_default());
+
+    persistence::save(GUILD_SETTINGS_STORE_NAME, &*store)
+}
+
+/// Returns the guild's duration limits, falling back to the configured URL limit
+pub async fn get_duration_limits(ctx: &Context, guild_id: GuildId) -> Result<DurationLimits> {
+    let settings = get_guild_settings(ctx, guild_id).await?;
+    let url = match settings.max_url_duration_secs {
+        Some(secs) => to_limit(secs),
+        None => get_url_config(ctx).await?.max_duration(),
+    };
+
+    Ok(DurationLimits {
+        sound: settings.max_sound_duration_secs.and_then(to_limit),
+        url,
+    })
+}
+
+/// Returns how long Pascal stays in a voice channel without playing anything,
+/// None if it never leaves on its own
+pub async fn get_idle_timeout(ctx: &Context, guild_id: GuildId) -> Result<Option<Duration>> {
+    let settings = get_guild_settings(ctx, guild_id).await?;
+
+    Ok(match settings.idle_timeout_mins {
+        Some(mins) => to_limit(mins * 60),
+        None => Some(DEFAULT_IDLE_TIMEOUT),
+    })
+}
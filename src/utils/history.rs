@@ -0,0 +1,173 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use log::error;
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+use songbird::tracks::{PlayMode, TrackHandle};
+
+use super::error::handle_error;
+
+/// Number of plays remembered per guild
+const HISTORY_SIZE: usize = 50;
+
+pub struct HistoryStore;
+
+impl TypeMapKey for HistoryStore {
+    type Value = Arc<Mutex<HashMap<GuildId, VecDeque<HistoryEntry>>>>;
+}
+
+/// The way a play was requested
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayInterface {
+    TextCommand,
+    SlashCommand,
+    Soundboard,
+    Intro,
+    Trigger,
+    Reaction,
+    Schedule,
+    Timer,
+    Quiz,
+}
+
+impl fmt::Display for PlayInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PlayInterface::TextCommand => "text command",
+            PlayInterface::SlashCommand => "slash command",
+            PlayInterface::Soundboard => "soundboard",
+            PlayInterface::Intro => "intro",
+            PlayInterface::Trigger => "trigger",
+            PlayInterface::Reaction => "reaction",
+            PlayInterface::Schedule => "schedule",
+            PlayInterface::Timer => "timer",
+            PlayInterface::Quiz => "quiz",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Who requested a play and how
+#[derive(Clone, Copy, Debug)]
+pub struct PlayOrigin {
+    pub user_id: UserId,
+    pub interface: PlayInterface,
+}
+
+impl PlayOrigin {
+    pub fn new(user_id: UserId, interface: PlayInterface) -> Self {
+        PlayOrigin { user_id, interface }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlayedMedia {
+    Sound(String),
+    Url(String),
+}
+
+impl fmt::Display for PlayedMedia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayedMedia::Sound(name) => write!(f, "**{}**", name),
+            PlayedMedia::Url(url) => write!(f, "<{}>", url),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub media: PlayedMedia,
+    pub origin: PlayOrigin,
+    pub played_at: SystemTime,
+    pub track: TrackHandle,
+}
+
+impl HistoryEntry {
+    /// Seconds since the unix epoch, as used by Discord's timestamp markdown
+    pub fn unix_timestamp(&self) -> u64 {
+        self.played_at
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+
+    pub async fn is_playing(&self) -> bool {
+        match self.track.get_info().await {
+            Ok(state) => state.playing == PlayMode::Play,
+            Err(_) => false,
+        }
+    }
+}
+
+async fn get_history_store(
+    ctx: &Context,
+) -> Result<Arc<Mutex<HashMap<GuildId, VecDeque<HistoryEntry>>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<HistoryStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get history store".to_string()))
+}
+
+/// Remembers a play that has just started. Failures are only logged,
+/// a play should never fail because it couldn't be recorded.
+pub async fn record_play(
+    ctx: &Context,
+    guild_id: GuildId,
+    media: PlayedMedia,
+    origin: PlayOrigin,
+    track: TrackHandle,
+) {
+    let store_lock = match get_history_store(ctx).await {
+        Ok(lock) => lock,
+        Err(err) => {
+            error!("Could not record play: {}", err);
+            return;
+        }
+    };
+    let mut store = store_lock.lock().await;
+
+    let history = store.entry(guild_id).or_default();
+    history.push_front(HistoryEntry {
+        media,
+        origin,
+        played_at: SystemTime::now(),
+        track,
+    });
+    history.truncate(HISTORY_SIZE);
+}
+
+/// Returns the guild's recent plays, newest first
+pub async fn get_history(ctx: &Context, guild_id: GuildId) -> Result<Vec<HistoryEntry>> {
+    let store_lock = get_history_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store
+        .get(&guild_id)
+        .map(|history| history.iter().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// Returns the plays of the guild that are still audible, newest first
+pub async fn get_currently_playing(ctx: &Context, guild_id: GuildId) -> Result<Vec<HistoryEntry>> {
+    let mut playing = Vec::new();
+    for entry in get_history(ctx, guild_id).await? {
+        if entry.is_playing().await {
+            playing.push(entry);
+        }
+    }
+
+    Ok(playing)
+}
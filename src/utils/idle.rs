@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::{error, info};
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId},
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{
+    error::handle_error, guild_settings::get_idle_timeout, recording::stop_recording,
+    tracks::get_active_tracks,
+};
+
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// When each guild Pascal is connected in last had something playing
+pub struct ActivityStore;
+
+impl TypeMapKey for ActivityStore {
+    type Value = Arc<Mutex<HashMap<GuildId, Instant>>>;
+}
+
+async fn get_activity_store(ctx: &Context) -> Result<Arc<Mutex<HashMap<GuildId, Instant>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<ActivityStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get activity store".to_string()))
+}
+
+/// Restarts the guild's idle timer
+pub async fn mark_active(ctx: &Context, guild_id: GuildId) {
+    match get_activity_store(ctx).await {
+        Ok(store_lock) => {
+            store_lock.lock().await.insert(guild_id, Instant::now());
+        }
+        Err(err) => error!("Could not mark guild {} as active: {}", guild_id, err),
+    }
+}
+
+/// Returns the voice channel Pascal is connected to in the guild, if any
+pub async fn get_connected_channel(ctx: &Context, guild_id: GuildId) -> Option<ChannelId> {
+    let manager = songbird::get(ctx).await?;
+    let call_lock = manager.get(guild_id)?;
+    let channel = call_lock.lock().await.current_channel()?;
+
+    Some(ChannelId(channel.0))
+}
+
+/// Disconnects from the guild's voice channel
+pub async fn leave_channel(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or_else(|| handle_error("Songbird Voice client not initialized".to_string()))?;
+
+    manager.remove(guild_id).await?;
+
+    if let Err(err) = stop_recording(ctx, guild_id).await {
+        error!("Could not stop recording in guild {}: {}", guild_id, err);
+    }
+    if let Ok(store_lock) = get_activity_store(ctx).await {
+        store_lock.lock().await.remove(&guild_id);
+    }
+
+    Ok(())
+}
+
+/// Returns true if at least one human is in the voice channel
+pub async fn has_listeners(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> bool {
+    let guild = match guild_id.to_guild_cached(&ctx.cache).await {
+        Some(guild) => guild,
+        None => return false,
+    };
+
+    for state in guild.voice_states.values() {
+        if state.channel_id != Some(channel_id) {
+            continue;
+        }
+
+        let is_bot = match &state.member {
+            Some(member) => member.user.bot,
+            None => ctx
+                .cache
+                .user(state.user_id)
+                .await
+                .map_or(false, |user| user.bot),
+        };
+        if !is_bot {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Leaves the guild's voice channel if no human is left in it
+pub async fn leave_if_alone(ctx: &Context, guild_id: GuildId) {
+    let channel_id = match get_connected_channel(ctx, guild_id).await {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+
+    // Without a cached guild there's no telling who is left, so stay
+    if guild_id.to_guild_cached(&ctx.cache).await.is_none() {
+        return;
+    }
+
+    if !has_listeners(ctx, guild_id, channel_id).await {
+        info!(
+            "Everyone left {} in guild {}, leaving too",
+            channel_id, guild_id
+        );
+        if let Err(err) = leave_channel(ctx, guild_id).await {
+            error!("Error leaving voice channel: {}", err);
+        }
+    }
+}
+
+/// Spawns a background task that leaves voice channels once nothing was played for a while
+pub fn start_idle_watcher(ctx: Context) {
+    // `ready` fires again on every reconnect, but one watcher is enough
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = leave_idle_channels(&ctx).await {
+                error!("Error checking for idle voice channels: {}", err);
+            }
+        }
+    });
+}
+
+async fn leave_idle_channels(ctx: &Context) -> Result<()> {
+    let store_lock = get_activity_store(ctx).await?;
+    let guilds: Vec<(GuildId, Instant)> = store_lock
+        .lock()
+        .await
+        .iter()
+        .map(|(guild_id, last_active)| (*guild_id, *last_active))
+        .collect();
+
+    for (guild_id, last_active) in guilds {
+        if get_connected_channel(ctx, guild_id).await.is_none() {
+            store_lock.lock().await.remove(&guild_id);
+            continue;
+        }
+
+        if !get_active_tracks(ctx, guild_id).await?.is_empty() {
+            mark_active(ctx, guild_id).await;
+            continue;
+        }
+
+        let timeout = match get_idle_timeout(ctx, guild_id).await? {
+            Some(timeout) => timeout,
+            None => continue,
+        };
+        if last_active.elapsed() >= timeout {
+            info!("Nothing played in guild {} for a while, leaving", guild_id);
+            leave_channel(ctx, guild_id).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,444 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+use tokio::{process::Command, time::timeout};
+
+use super::{
+    config::ImportConfig,
+    effects::{probe_duration, render_to_file, AudioOptions},
+    error::handle_error,
+    persistence,
+    sound_files::{get_sound_files, reserve_sound_path, validate_sound_name, MAX_CLIP_LENGTH},
+    tracks::format_timestamp,
+    url_source::{classify_url, is_url, UrlKind},
+};
+
+pub const SOUND_SOURCES_STORE_NAME: &str = "sound_sources";
+
+const IMPORT_DIR: &str = "./data/imports";
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Numbers the scratch directories, so imports running at once never share one
+static IMPORT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub struct ImportStore;
+
+impl TypeMapKey for ImportStore {
+    type Value = ImportConfig;
+}
+
+pub struct SoundSourcesStore;
+
+impl TypeMapKey for SoundSourcesStore {
+    type Value = Arc<Mutex<SoundSources>>;
+}
+
+/// Where imported sounds came from, keyed by sound name
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SoundSources {
+    pub sounds: HashMap<String, SoundSource>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SoundSource {
+    pub url: String,
+    /// Title the downloader reported for the URL, if any
+    pub title: Option<String>,
+    pub start_ms: Option<u64>,
+    pub end_ms: Option<u64>,
+    pub imported_by: u64,
+    /// Seconds since the unix epoch
+    pub imported_at: u64,
+}
+
+async fn get_sources_store(ctx: &Context) -> Result<Arc<Mutex<SoundSources>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<SoundSourcesStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get sound sources store".to_string()))
+}
+
+async fn get_import_config(ctx: &Context) -> Result<ImportConfig> {
+    ctx.data
+        .read()
+        .await
+        .get::<ImportStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get import config".to_string()))
+}
+
+/// Returns the configured youtube-dl compatible binary, which everything downloading goes through
+pub async fn get_downloader(ctx: &Context) -> Result<String> {
+    Ok(get_import_config(ctx).await?.downloader)
+}
+
+pub async fn get_sound_source(ctx: &Context, name: &str) -> Result<Option<SoundSource>> {
+    let store_lock = get_sources_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store.sounds.get(name).cloned())
+}
+
+/// Downloads the audio behind a URL, trims it to the options' offsets, normalizes it
+/// and adds it to the library under the given name.
+/// Imported sounds may be `MAX_CLIP_LENGTH` long, just like clips.
+/// The inner error describes why the sound can't be imported.
+pub async fn import_sound(
+    ctx: &Context,
+    guild_id: GuildId,
+    url: &str,
+    name: &str,
+    options: &AudioOptions,
+    user_id: UserId,
+) -> Result<std::result::Result<SoundSource, String>> {
+    if let Err(err) = validate_sound_name(name) {
+        return Ok(Err(err));
+    }
+    if get_sound_files()?.contains_key(name) {
+        return Ok(Err(format!("There already is a sound called **{}**", name)));
+    }
+    if !is_url(url) {
+        return Ok(Err("Please give me a http(s) URL".to_string()));
+    }
+    match classify_url(ctx, url, Some(guild_id)).await? {
+        Ok(UrlKind::Stream) => return Ok(Err("Live streams can't be imported".to_string())),
+        Ok(_) => (),
+        Err(reason) => return Ok(Err(reason)),
+    }
+
+    let config = get_import_config(ctx).await?;
+    let options = AudioOptions {
+        normalize: config.normalize,
+        ..options.clone()
+    };
+
+    let destination = match reserve_sound_path(name)? {
+        Some(destination) => destination,
+        None => return Ok(Err(format!("There already is a sound called **{}**", name))),
+    };
+    let download_dir = scratch_dir(name);
+    let result = download_sound(&config, url, &options, &download_dir, &destination).await;
+    if !matches!(result, Ok(Ok(_))) {
+        // An empty file would show up as a broken sound
+        let _ = fs::remove_file(&destination);
+    }
+    let title = match result? {
+        Ok(title) => title,
+        Err(reason) => return Ok(Err(reason)),
+    };
+
+    let source = SoundSource {
+        url: url.to_owned(),
+        title,
+        start_ms: options.start.map(|start| start.as_millis() as u64),
+        end_ms: options.end.map(|end| end.as_millis() as u64),
+        imported_by: *user_id.as_u64(),
+        imported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default(),
+    };
+
+    let store_lock = get_sources_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+    store.sounds.insert(name.to_owned(), source.clone());
+    persistence::save(SOUND_SOURCES_STORE_NAME, &*store)?;
+
+    info!("Imported sound {} from {}", name, url);
+
+    Ok(Ok(source))
+}
+
+/// Every import gets its own scratch directory, so the download is easy to find
+fn scratch_dir(name: &str) -> PathBuf {
+    let number = IMPORT_COUNTER.fetch_add(1, Ordering::Relaxed);
+    PathBuf::from(IMPORT_DIR).join(format!("{}-{}", name, number))
+}
+
+/// Downloads the URL into the scratch directory and renders it to the destination,
+/// cleaning up the scratch directory afterwards. Returns the title the downloader reported.
+async fn download_sound(
+    config: &ImportConfig,
+    url: &str,
+    options: &AudioOptions,
+    download_dir: &Path,
+    destination: &Path,
+) -> Result<std::result::Result<Option<String>, String>> {
+    fs::create_dir_all(download_dir)
+        .with_context(|| format!("Error creating import directory {:?}", download_dir))?;
+
+    let result = download_and_render(config, url, options, download_dir, destination).await;
+
+    if let Err(err) = fs::remove_dir_all(download_dir) {
+        warn!(
+            "Could not clean up import directory {:?}: {}",
+            download_dir, err
+        );
+    }
+
+    result
+}
+
+/// Returns the title the downloader reported
+async fn download_and_render(
+    config: &ImportConfig,
+    url: &str,
+    options: &AudioOptions,
+    download_dir: &Path,
+    destination: &Path,
+) -> Result<std::result::Result<Option<String>, String>> {
+    let output_template = download_dir.join("source.%(ext)s");
+    let download = Command::new(&config.downloader)
+        .args(&[
+            "--no-playlist",
+            "-f",
+            "bestaudio/best",
+            "--print-json",
+            "-o",
+        ])
+        .arg(&output_template)
+        .arg(url)
+        .kill_on_drop(true)
+        .output();
+
+    let output = match timeout(DOWNLOAD_TIMEOUT, download).await {
+        Ok(output) => output.with_context(|| format!("Error running {}", config.downloader))?,
+        Err(_) => return Ok(Err("Downloading took too long".to_string())),
+    };
+
+    if !output.status.success() {
+        warn!(
+            "{} could not download {}: {}",
+            config.downloader,
+            url,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Ok(Err(format!("Could not download <{}>", url)));
+    }
+
+    let downloaded = fs::read_dir(download_dir)
+        .with_context(|| format!("Error reading import directory {:?}", download_dir))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.is_file());
+    let downloaded = match downloaded {
+        Some(path) => path,
+        None => return Ok(Err(format!("Nothing was downloaded from <{}>", url))),
+    };
+
+    // Without a known length there has to be an end to cut at
+    let length = match probe_duration(&downloaded).await {
+        Some(Some(length)) => Some(options.played_length(length)),
+        _ => options.end.map(|end| options.played_length(end)),
+    };
+    match length {
+        Some(length) if length <= MAX_CLIP_LENGTH => (),
+        Some(length) => {
+            return Ok(Err(format!(
+                "That sound would be {} long, imported sounds may be {} at most",
+                format_timestamp(length),
+                format_timestamp(MAX_CLIP_LENGTH)
+            )))
+        }
+        None => {
+            return Ok(Err(
+                "I can't tell how long that is, please set an end to cut it at".to_string(),
+            ))
+        }
+    }
+
+    render_to_file(&downloaded, options, destination).await?;
+
+    Ok(Ok(parse_title(&String::from_utf8_lossy(&output.stdout))))
+}
+
+/// Picks the title out of the info JSON the downloader printed, if it printed one
+fn parse_title(stdout: &str) -> Option<String> {
+    stdout
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<Value>(line).ok())
+        .and_then(|info| info.get("title")?.as_str().map(|title| title.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    const URL: &str = "https://example.com/sound";
+
+    fn test_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pascal-import-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a shell script standing in for youtube-dl and returns a config using it
+    fn stub_downloader(dir: &Path, body: &str) -> ImportConfig {
+        let path = dir.join("downloader");
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        ImportConfig {
+            downloader: path.to_string_lossy().into_owned(),
+            normalize: false,
+        }
+    }
+
+    /// Stub downloader generating a sine wave of the given length where the output template points
+    fn sine_downloader(dir: &Path, secs: u64) -> ImportConfig {
+        stub_downloader(
+            dir,
+            &format!(
+                r#"while [ $# -gt 1 ]; do
+    if [ "$1" = "-o" ]; then template="$2"; fi
+    shift
+done
+ffmpeg -loglevel error -f lavfi -i sine=duration={} "$(echo "$template" | sed 's/%(ext)s/wav/')" || exit 1
+echo '{{"title": "Stub sound"}}'"#,
+                secs
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn imports_what_the_downloader_fetched() {
+        let dir = test_dir("fetched");
+        let config = sine_downloader(&dir, 1);
+        let download_dir = dir.join("scratch");
+        let destination = dir.join("sound.mp3");
+
+        let result = download_sound(
+            &config,
+            URL,
+            &AudioOptions::default(),
+            &download_dir,
+            &destination,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, Ok(Some("Stub sound".to_string())));
+        assert!(destination.is_file());
+        assert!(!download_dir.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_sounds_longer_than_clips() {
+        let dir = test_dir("long");
+        let config = sine_downloader(&dir, MAX_CLIP_LENGTH.as_secs() + 10);
+        let download_dir = dir.join("scratch");
+        let destination = dir.join("sound.mp3");
+
+        let result = download_sound(
+            &config,
+            URL,
+            &AudioOptions::default(),
+            &download_dir,
+            &destination,
+        )
+        .await
+        .unwrap();
+        assert!(result.is_err());
+        assert!(!destination.exists());
+
+        // Trimmed down it fits
+        let options = AudioOptions {
+            end: Some(Duration::from_secs(5)),
+            ..AudioOptions::default()
+        };
+        let result = download_sound(&config, URL, &options, &download_dir, &destination)
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+        assert!(destination.is_file());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_failed_downloads() {
+        let dir = test_dir("failed");
+        let config = stub_downloader(&dir, "echo 'ERROR: Unsupported URL' >&2\nexit 1");
+        let download_dir = dir.join("scratch");
+        let destination = dir.join("sound.mp3");
+
+        let result = download_sound(
+            &config,
+            URL,
+            &AudioOptions::default(),
+            &download_dir,
+            &destination,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, Err(format!("Could not download <{}>", URL)));
+        assert!(!destination.exists());
+        assert!(!download_dir.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_empty_downloads() {
+        let dir = test_dir("empty");
+        let config = stub_downloader(&dir, "echo '{\"title\": \"Nothing\"}'");
+        let download_dir = dir.join("scratch");
+        let destination = dir.join("sound.mp3");
+
+        let result = download_sound(
+            &config,
+            URL,
+            &AudioOptions::default(),
+            &download_dir,
+            &destination,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            result,
+            Err(format!("Nothing was downloaded from <{}>", URL))
+        );
+        assert!(!download_dir.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imports_of_the_same_name_get_their_own_scratch_dir() {
+        assert_ne!(scratch_dir("airhorn"), scratch_dir("airhorn"));
+    }
+
+    #[test]
+    fn parses_the_last_printed_title() {
+        assert_eq!(
+            parse_title("[download] 100%\n{\"title\": \"Airhorn\", \"id\": \"abc\"}\n"),
+            Some("Airhorn".to_string())
+        );
+        assert_eq!(parse_title("{\"id\": \"abc\"}"), None);
+        assert_eq!(parse_title(""), None);
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    prelude::{Mutex, TypeMapKey},
+};
+use tokio::{process::Command, time::timeout};
+
+use super::{config::CacheConfig, error::handle_error, import::get_downloader, persistence};
+
+pub const MEDIA_CACHE_STORE_NAME: &str = "media_cache";
+
+const CACHE_DIR: &str = "./data/cache";
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static CACHE_EVICTIONS: AtomicU64 = AtomicU64::new(0);
+
+pub struct MediaCacheStore;
+
+impl TypeMapKey for MediaCacheStore {
+    type Value = Arc<Mutex<MediaCache>>;
+}
+
+pub struct MediaCache {
+    config: CacheConfig,
+    index: CacheIndex,
+    /// URLs currently being downloaded, so a URL played twice in a row is only fetched once
+    downloading: HashSet<String>,
+}
+
+impl MediaCache {
+    pub fn new(config: CacheConfig, index: CacheIndex) -> Self {
+        MediaCache {
+            config,
+            index,
+            downloading: HashSet::new(),
+        }
+    }
+}
+
+/// The persisted part of the cache, keyed by URL
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CacheIndex {
+    pub entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CacheEntry {
+    pub file_name: String,
+    pub size: u64,
+    /// Seconds since the unix epoch
+    pub downloaded_at: u64,
+    /// Seconds since the unix epoch
+    pub last_used: u64,
+}
+
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub size: u64,
+}
+
+async fn get_cache_store(ctx: &Context) -> Result<Arc<Mutex<MediaCache>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<MediaCacheStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get media cache store".to_string()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Names cache files after a FNV-1a hash of their URL, which stays stable across builds
+fn cache_file_name(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    format!("{:016x}", hash)
+}
+
+fn cache_path(file_name: &str) -> PathBuf {
+    PathBuf::from(CACHE_DIR).join(file_name)
+}
+
+/// Returns the local copy of the URL's audio if a fresh one is cached
+pub async fn get_cached(ctx: &Context, url: &str) -> Option<PathBuf> {
+    let store_lock = match get_cache_store(ctx).await {
+        Ok(store_lock) => store_lock,
+        Err(_) => return None,
+    };
+    let mut cache = store_lock.lock().await;
+    if !cache.config.enabled {
+        return None;
+    }
+
+    let ttl = cache.config.ttl().as_secs();
+    let now = unix_now();
+    let hit = match cache.index.entries.get_mut(url) {
+        Some(entry) => {
+            let path = cache_path(&entry.file_name);
+            if now.saturating_sub(entry.downloaded_at) <= ttl && path.exists() {
+                entry.last_used = now;
+                Some(path)
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+
+    match hit {
+        Some(path) => {
+            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            info!("Media cache hit for {}", url);
+            save_index(&cache);
+
+            Some(path)
+        }
+        None => {
+            CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+            info!("Media cache miss for {}", url);
+            if cache.index.entries.contains_key(url) {
+                evict(&mut cache, url, "expired");
+                save_index(&cache);
+            }
+
+            None
+        }
+    }
+}
+
+/// Downloads the URL's audio into the cache without holding up playback
+pub fn cache_in_background(ctx: Context, url: String) {
+    tokio::spawn(async move {
+        if let Err(err) = download_into_cache(&ctx, &url).await {
+            warn!("Could not cache {}: {}", url, err);
+        }
+    });
+}
+
+async fn download_into_cache(ctx: &Context, url: &str) -> Result<()> {
+    let downloader = get_downloader(ctx).await?;
+    let store_lock = get_cache_store(ctx).await?;
+    let max_size = {
+        let mut cache = store_lock.lock().await;
+        if !cache.config.enabled
+            || cache.index.entries.contains_key(url)
+            || !cache.downloading.insert(url.to_owned())
+        {
+            return Ok(());
+        }
+
+        cache.config.max_size_bytes()
+    };
+
+    let file_name = cache_file_name(url);
+    let path = cache_path(&file_name);
+    let result = download(&downloader, url, &path, max_size).await;
+
+    let mut cache = store_lock.lock().await;
+    cache.downloading.remove(url);
+
+    if let Err(err) = result {
+        // youtube-dl leaves its partial download next to the target
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("part"));
+        return Err(err);
+    }
+
+    let size = fs::metadata(&path)
+        .with_context(|| format!("Error reading cached file {:?}", path))?
+        .len();
+    let now = unix_now();
+    cache.index.entries.insert(
+        url.to_owned(),
+        CacheEntry {
+            file_name,
+            size,
+            downloaded_at: now,
+            last_used: now,
+        },
+    );
+    info!("Cached {} ({} KiB)", url, size / 1024);
+
+    enforce_budget(&mut cache);
+    save_index(&cache);
+
+    Ok(())
+}
+
+async fn download(downloader: &str, url: &str, path: &Path, max_size: u64) -> Result<()> {
+    fs::create_dir_all(CACHE_DIR).with_context(|| "Error creating media cache directory")?;
+
+    let download = Command::new(downloader)
+        .args(&["--no-playlist", "-f", "bestaudio/best", "--max-filesize"])
+        .arg(max_size.to_string())
+        .arg("-o")
+        .arg(path)
+        .arg(url)
+        .kill_on_drop(true)
+        .output();
+
+    let output = timeout(DOWNLOAD_TIMEOUT, download)
+        .await
+        .map_err(|_| handle_error(format!("Downloading {} timed out", url)))?
+        .with_context(|| format!("Error running {}", downloader))?;
+
+    // youtube-dl skips files above the size limit without failing
+    if !output.status.success() || !path.exists() {
+        return Err(handle_error(format!(
+            "{} could not download {}: {}",
+            downloader,
+            url,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
+/// Drops expired entries, then the least recently played ones until the cache fits its budget
+fn enforce_budget(cache: &mut MediaCache) {
+    let ttl = cache.config.ttl().as_secs();
+    let now = unix_now();
+    let expired: Vec<String> = cache
+        .index
+        .entries
+        .iter()
+        .filter(|(_, entry)| now.saturating_sub(entry.downloaded_at) > ttl)
+        .map(|(url, _)| url.clone())
+        .collect();
+    for url in expired {
+        evict(cache, &url, "expired");
+    }
+
+    let max_size = cache.config.max_size_bytes();
+    let mut size: u64 = cache.index.entries.values().map(|entry| entry.size).sum();
+    while size > max_size {
+        let oldest = cache
+            .index
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(url, entry)| (url.clone(), entry.size));
+
+        match oldest {
+            Some((url, entry_size)) => {
+                evict(cache, &url, "over budget");
+                size -= entry_size;
+            }
+            None => break,
+        }
+    }
+}
+
+fn evict(cache: &mut MediaCache, url: &str, reason: &str) {
+    if let Some(entry) = cache.index.entries.remove(url) {
+        let path = cache_path(&entry.file_name);
+        if let Err(err) = fs::remove_file(&path) {
+            warn!("Could not delete cached file {:?}: {}", path, err);
+        }
+
+        CACHE_EVICTIONS.fetch_add(1, Ordering::Relaxed);
+        info!("Evicted {} from the media cache ({})", url, reason);
+    }
+}
+
+fn save_index(cache: &MediaCache) {
+    if let Err(err) = persistence::save(MEDIA_CACHE_STORE_NAME, &cache.index) {
+        warn!("Could not save the media cache index: {}", err);
+    }
+}
+
+/// Counters since startup along with the current size of the cache
+pub async fn get_cache_metrics(ctx: &Context) -> Result<CacheMetrics> {
+    let store_lock = get_cache_store(ctx).await?;
+    let cache = store_lock.lock().await;
+
+    Ok(CacheMetrics {
+        hits: CACHE_HITS.load(Ordering::Relaxed),
+        misses: CACHE_MISSES.load(Ordering::Relaxed),
+        evictions: CACHE_EVICTIONS.load(Ordering::Relaxed),
+        entries: cache.index.entries.len(),
+        size: cache.index.entries.values().map(|entry| entry.size).sum(),
+    })
+}
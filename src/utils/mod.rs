@@ -1,5 +1,30 @@
+pub mod combos;
 pub mod config;
 pub mod discord;
+pub mod effects;
 pub mod error;
+pub mod favorites;
+pub mod follow;
+pub mod fuzzy_lookup;
+pub mod guild_settings;
+pub mod history;
+pub mod idle;
+pub mod import;
+pub mod media_cache;
+pub mod persistence;
+pub mod playlists;
+pub mod quiz;
+pub mod random;
+pub mod reactions;
+pub mod recording;
+pub mod schedule;
+pub mod sequence;
 pub mod sound_files;
-pub mod fuzzy_lookup;
\ No newline at end of file
+pub mod soundboard;
+pub mod stats;
+pub mod timers;
+pub mod tracks;
+pub mod triggers;
+pub mod tts;
+pub mod url_source;
+pub mod youtube_search;
@@ -0,0 +1,40 @@
+use anyhow::{Context as AnyhowCtx, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, path::PathBuf};
+
+const DATA_DIR: &str = "./data";
+
+fn store_path(name: &str) -> PathBuf {
+    PathBuf::from(DATA_DIR).join(format!("{}.json", name))
+}
+
+/// Loads the named store from the data directory.
+/// Returns the default value if the store has never been written.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T> {
+    let path = store_path(name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let file = fs::File::open(&path).with_context(|| format!("Error opening store {:?}", path))?;
+    let value =
+        serde_json::from_reader(file).with_context(|| format!("Error parsing store {:?}", path))?;
+
+    Ok(value)
+}
+
+/// Writes the named store to the data directory, replacing its previous contents
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    fs::create_dir_all(DATA_DIR).with_context(|| "Error creating data directory")?;
+
+    // Write to a temporary file first so a crash mid-write can't corrupt the store
+    let path = store_path(name);
+    let tmp_path = path.with_extension("json.tmp");
+    let file = fs::File::create(&tmp_path)
+        .with_context(|| format!("Error creating store {:?}", tmp_path))?;
+    serde_json::to_writer_pretty(file, value)
+        .with_context(|| format!("Error writing store {:?}", tmp_path))?;
+    fs::rename(&tmp_path, &path).with_context(|| format!("Error replacing store {:?}", path))?;
+
+    Ok(())
+}
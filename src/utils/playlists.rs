@@ -0,0 +1,339 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use log::error;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+use songbird::tracks::TrackHandle;
+
+use super::{
+    discord::{enqueue_sound, enqueue_url},
+    error::handle_error,
+    history::PlayOrigin,
+    persistence,
+    sequence::wait_for_track_end,
+    sound_files::get_sound_files,
+    url_source::is_url,
+};
+
+pub const PLAYLISTS_STORE_NAME: &str = "playlists";
+pub const MAX_PLAYLIST_LENGTH: usize = 100;
+
+pub struct PlaylistsStore;
+
+impl TypeMapKey for PlaylistsStore {
+    type Value = Arc<Mutex<Playlists>>;
+}
+
+/// Personal playlists per user and shared playlists per guild
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Playlists {
+    pub users: HashMap<u64, HashMap<String, Playlist>>,
+    pub guilds: HashMap<u64, HashMap<String, Playlist>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Playlist {
+    pub owner: u64,
+    /// Library sound names and URLs
+    pub items: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaylistScope {
+    Personal,
+    Shared,
+}
+
+impl Playlists {
+    fn scope_mut(
+        &mut self,
+        scope: PlaylistScope,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> &mut HashMap<String, Playlist> {
+        match scope {
+            PlaylistScope::Personal => self.users.entry(*user_id.as_u64()).or_default(),
+            PlaylistScope::Shared => self.guilds.entry(*guild_id.as_u64()).or_default(),
+        }
+    }
+
+    /// Finds a playlist by name, preferring the user's own playlists over shared ones
+    fn find_mut(
+        &mut self,
+        guild_id: GuildId,
+        user_id: UserId,
+        name: &str,
+    ) -> Option<(PlaylistScope, &mut Playlist)> {
+        if let Some(playlist) = self
+            .users
+            .get_mut(user_id.as_u64())
+            .and_then(|playlists| playlists.get_mut(name))
+        {
+            return Some((PlaylistScope::Personal, playlist));
+        }
+
+        self.guilds
+            .get_mut(guild_id.as_u64())
+            .and_then(|playlists| playlists.get_mut(name))
+            .map(|playlist| (PlaylistScope::Shared, playlist))
+    }
+}
+
+pub enum PlaylistError {
+    NotFound,
+    AlreadyExists,
+    UnknownItem,
+    Full,
+    NotOwner,
+}
+
+/// Personal playlists are only ever found for their owner, shared ones may be changed
+/// by their owner or an admin
+fn may_change(scope: PlaylistScope, playlist: &Playlist, user_id: UserId, is_admin: bool) -> bool {
+    scope == PlaylistScope::Personal || playlist.owner == *user_id.as_u64() || is_admin
+}
+
+async fn get_playlists_store(ctx: &Context) -> Result<Arc<Mutex<Playlists>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<PlaylistsStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get playlists store".to_string()))
+}
+
+pub async fn create_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    name: &str,
+    scope: PlaylistScope,
+) -> Result<std::result::Result<(), PlaylistError>> {
+    let store_lock = get_playlists_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let playlists = store.scope_mut(scope, guild_id, user_id);
+    if playlists.contains_key(name) {
+        return Ok(Err(PlaylistError::AlreadyExists));
+    }
+    playlists.insert(
+        name.to_owned(),
+        Playlist {
+            owner: *user_id.as_u64(),
+            items: Vec::new(),
+        },
+    );
+
+    persistence::save(PLAYLISTS_STORE_NAME, &*store)?;
+
+    Ok(Ok(()))
+}
+
+/// Appends a library sound or URL to the playlist.
+/// Shared playlists can only be changed by their owner or an admin.
+pub async fn add_to_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    name: &str,
+    item: &str,
+    is_admin: bool,
+) -> Result<std::result::Result<usize, PlaylistError>> {
+    if !is_url(item) && !get_sound_files()?.contains_key(item) {
+        return Ok(Err(PlaylistError::UnknownItem));
+    }
+
+    let store_lock = get_playlists_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let playlist = match store.find_mut(guild_id, user_id, name) {
+        Some((scope, playlist)) => {
+            if !may_change(scope, playlist, user_id, is_admin) {
+                return Ok(Err(PlaylistError::NotOwner));
+            }
+            playlist
+        }
+        None => return Ok(Err(PlaylistError::NotFound)),
+    };
+    if playlist.items.len() >= MAX_PLAYLIST_LENGTH {
+        return Ok(Err(PlaylistError::Full));
+    }
+    playlist.items.push(item.to_owned());
+    let length = playlist.items.len();
+
+    persistence::save(PLAYLISTS_STORE_NAME, &*store)?;
+
+    Ok(Ok(length))
+}
+
+/// Removes an item from the playlist, given either as its position or by value.
+/// Shared playlists can only be changed by their owner or an admin.
+pub async fn remove_from_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    name: &str,
+    item: &str,
+    is_admin: bool,
+) -> Result<std::result::Result<String, PlaylistError>> {
+    let store_lock = get_playlists_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let playlist = match store.find_mut(guild_id, user_id, name) {
+        Some((scope, playlist)) => {
+            if !may_change(scope, playlist, user_id, is_admin) {
+                return Ok(Err(PlaylistError::NotOwner));
+            }
+            playlist
+        }
+        None => return Ok(Err(PlaylistError::NotFound)),
+    };
+
+    let index = match item.parse::<usize>() {
+        Ok(position) if (1..=playlist.items.len()).contains(&position) => Some(position - 1),
+        _ => playlist.items.iter().position(|existing| existing == item),
+    };
+    let removed = match index {
+        Some(index) => playlist.items.remove(index),
+        None => return Ok(Err(PlaylistError::UnknownItem)),
+    };
+
+    persistence::save(PLAYLISTS_STORE_NAME, &*store)?;
+
+    Ok(Ok(removed))
+}
+
+/// Deletes a playlist. Shared playlists can only be deleted by their owner or an admin.
+pub async fn delete_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    name: &str,
+    is_admin: bool,
+) -> Result<std::result::Result<(), PlaylistError>> {
+    let store_lock = get_playlists_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let scope = match store.find_mut(guild_id, user_id, name) {
+        Some((scope, playlist)) => {
+            if !may_change(scope, playlist, user_id, is_admin) {
+                return Ok(Err(PlaylistError::NotOwner));
+            }
+            scope
+        }
+        None => return Ok(Err(PlaylistError::NotFound)),
+    };
+    store.scope_mut(scope, guild_id, user_id).remove(name);
+
+    persistence::save(PLAYLISTS_STORE_NAME, &*store)?;
+
+    Ok(Ok(()))
+}
+
+pub async fn get_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    name: &str,
+) -> Result<Option<(PlaylistScope, Playlist)>> {
+    let store_lock = get_playlists_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    Ok(store
+        .find_mut(guild_id, user_id, name)
+        .map(|(scope, playlist)| (scope, playlist.clone())))
+}
+
+/// Returns the names of the user's own playlists and of the guild's shared playlists
+pub async fn get_playlist_names(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let store_lock = get_playlists_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    let sorted_names = |playlists: Option<&HashMap<String, Playlist>>| {
+        let mut names: Vec<String> = playlists
+            .map(|playlists| playlists.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort_unstable();
+        names
+    };
+
+    Ok((
+        sorted_names(store.users.get(user_id.as_u64())),
+        sorted_names(store.guilds.get(guild_id.as_u64())),
+    ))
+}
+
+/// Queues the playlist's items in the background. With `looping` the playlist is queued
+/// again whenever its last item finished, until one of its tracks gets stopped.
+pub fn play_playlist(
+    ctx: &Context,
+    guild_id: GuildId,
+    playlist: Playlist,
+    shuffle: bool,
+    looping: bool,
+    origin: PlayOrigin,
+) {
+    let ctx = ctx.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let mut items = playlist.items.clone();
+            if shuffle {
+                items.shuffle(&mut rand::thread_rng());
+            }
+
+            let last_track = match enqueue_items(&ctx, guild_id, &items, origin).await {
+                Ok(Some(track)) => track,
+                Ok(None) => return,
+                Err(err) => {
+                    error!("Error queueing playlist: {}", err);
+                    return;
+                }
+            };
+
+            if !looping || !wait_for_track_end(&last_track).await {
+                return;
+            }
+        }
+    });
+}
+
+/// Returns the handle of the last queued track, if any item could be queued
+async fn enqueue_items(
+    ctx: &Context,
+    guild_id: GuildId,
+    items: &[String],
+    origin: PlayOrigin,
+) -> Result<Option<TrackHandle>> {
+    let sound_files = get_sound_files()?;
+    let mut last_track = None;
+
+    for item in items {
+        let result = if is_url(item) {
+            enqueue_url(ctx, guild_id, item, origin).await
+        } else {
+            match sound_files.get(item) {
+                Some(file) => enqueue_sound(ctx, guild_id, file, origin).await,
+                // Sounds may have been removed from the library since they were added
+                None => continue,
+            }
+        };
+
+        match result {
+            Ok(track) => last_track = Some(track),
+            Err(err) => error!("Error queueing playlist item {}: {}", item, err),
+        }
+    }
+
+    Ok(last_track)
+}
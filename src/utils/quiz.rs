@@ -0,0 +1,373 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::error;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+use tokio::{sync::Notify, time::timeout};
+
+use super::{
+    discord::play_sound,
+    effects::AudioOptions,
+    error::{check_msg, handle_error},
+    fuzzy_lookup::get_best_match,
+    history::{PlayInterface, PlayOrigin},
+    persistence,
+    sound_files::get_sound_files,
+};
+
+pub const QUIZ_SCORES_STORE_NAME: &str = "quiz_scores";
+
+pub const DEFAULT_ROUNDS: usize = 5;
+pub const MAX_ROUNDS: usize = 20;
+pub const SCOREBOARD_SIZE: usize = 10;
+
+const ROUND_TIMEOUT: Duration = Duration::from_secs(30);
+const ROUND_PAUSE: Duration = Duration::from_secs(3);
+/// Points for an instant answer, one less for every `POINT_DECAY` it takes
+const MAX_POINTS: u64 = 10;
+const POINT_DECAY: Duration = Duration::from_secs(3);
+
+/// Running quiz games, at most one per guild
+pub struct QuizStore;
+
+impl TypeMapKey for QuizStore {
+    type Value = Arc<Mutex<HashMap<GuildId, QuizGame>>>;
+}
+
+pub struct QuizScoresStore;
+
+impl TypeMapKey for QuizScoresStore {
+    type Value = Arc<Mutex<QuizScores>>;
+}
+
+/// Points every player collected over all quizzes, per guild
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct QuizScores {
+    pub guilds: HashMap<u64, HashMap<u64, u64>>,
+}
+
+pub struct QuizGame {
+    /// Text channel the quiz is played in
+    channel_id: ChannelId,
+    started_by: UserId,
+    /// Sounds of the rounds still to come
+    sounds: Vec<String>,
+    /// Every sound name, which guesses are matched against
+    library: Vec<String>,
+    rounds: usize,
+    round: usize,
+    current: Option<Round>,
+    scores: HashMap<UserId, u64>,
+    stopped: bool,
+    /// Wakes the game up when a round is solved or the game is stopped
+    notify: Arc<Notify>,
+}
+
+struct Round {
+    sound: String,
+    started_at: Instant,
+}
+
+/// A correct answer
+pub struct Solution {
+    pub sound: String,
+    pub points: u64,
+}
+
+async fn get_quiz_store(ctx: &Context) -> Result<Arc<Mutex<HashMap<GuildId, QuizGame>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<QuizStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get quiz store".to_string()))
+}
+
+async fn get_scores_store(ctx: &Context) -> Result<Arc<Mutex<QuizScores>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<QuizScoresStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get quiz scores store".to_string()))
+}
+
+/// Starts a quiz with random sounds, optionally from one category only.
+/// Pascal has to be in a voice channel of the guild already.
+/// Returns the number of rounds, or the reason the quiz can't start.
+pub async fn start_quiz(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    started_by: UserId,
+    rounds: usize,
+    category: Option<&str>,
+) -> Result<std::result::Result<usize, String>> {
+    let store_lock = get_quiz_store(ctx).await?;
+    let mut games = store_lock.lock().await;
+    if games.contains_key(&guild_id) {
+        return Ok(Err(
+            "A quiz is already running, stop it first or play along.".to_string(),
+        ));
+    }
+
+    let sound_files = get_sound_files()?;
+    let mut sounds: Vec<String> = sound_files
+        .iter()
+        .filter(|(_, file)| match category {
+            Some(category) => file
+                .category
+                .as_ref()
+                .map_or(false, |c| c.eq_ignore_ascii_case(category)),
+            None => true,
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    if sounds.is_empty() {
+        return Ok(Err(match category {
+            Some(category) => format!("There are no sounds in the category **{}**.", category),
+            None => "There are no sounds to guess.".to_string(),
+        }));
+    }
+
+    sounds.shuffle(&mut rand::thread_rng());
+    sounds.truncate(rounds.clamp(1, MAX_ROUNDS));
+    let rounds = sounds.len();
+
+    games.insert(
+        guild_id,
+        QuizGame {
+            channel_id,
+            started_by,
+            sounds,
+            library: sound_files.into_iter().map(|(name, _)| name).collect(),
+            rounds,
+            round: 0,
+            current: None,
+            scores: HashMap::new(),
+            stopped: false,
+            notify: Arc::new(Notify::new()),
+        },
+    );
+
+    let game_ctx = ctx.clone();
+    tokio::spawn(async move {
+        if let Err(err) = run_quiz(&game_ctx, guild_id).await {
+            error!("Error running quiz: {}", err);
+
+            // The guild must be able to start another quiz
+            if let Ok(store_lock) = get_quiz_store(&game_ctx).await {
+                store_lock.lock().await.remove(&guild_id);
+            }
+        }
+    });
+
+    Ok(Ok(rounds))
+}
+
+/// Stops the guild's quiz after the current round. Returns false if there is none.
+pub async fn stop_quiz(ctx: &Context, guild_id: GuildId) -> Result<bool> {
+    let store_lock = get_quiz_store(ctx).await?;
+    let mut games = store_lock.lock().await;
+
+    Ok(match games.get_mut(&guild_id) {
+        Some(game) => {
+            game.stopped = true;
+            game.notify.notify_one();
+            true
+        }
+        None => false,
+    })
+}
+
+/// Checks a guess posted in the channel against the current round.
+/// The first correct guess ends the round and scores more points the faster it came.
+pub async fn submit_answer(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    user_id: UserId,
+    guess: &str,
+) -> Result<Option<Solution>> {
+    let store_lock = get_quiz_store(ctx).await?;
+    let mut games = store_lock.lock().await;
+
+    let game = match games.get_mut(&guild_id) {
+        Some(game) if game.channel_id == channel_id && !game.stopped => game,
+        _ => return Ok(None),
+    };
+    let round = match &game.current {
+        Some(round) if is_correct(guess, &round.sound, &game.library) => round,
+        _ => return Ok(None),
+    };
+
+    let decay = (round.started_at.elapsed().as_secs() / POINT_DECAY.as_secs()).min(MAX_POINTS - 1);
+    let solution = Solution {
+        sound: round.sound.clone(),
+        points: MAX_POINTS - decay,
+    };
+
+    game.current = None;
+    *game.scores.entry(user_id).or_default() += solution.points;
+    game.notify.notify_one();
+
+    Ok(Some(solution))
+}
+
+/// A guess is right if it names the sound, or if the sound is the library's best match for it.
+/// Guesses much shorter than the name don't count, so nobody wins by typing single letters.
+fn is_correct(guess: &str, sound: &str, library: &[String]) -> bool {
+    let guess = guess.trim();
+    if guess.eq_ignore_ascii_case(sound) {
+        return true;
+    }
+    if guess.chars().count() * 2 < sound.chars().count() {
+        return false;
+    }
+
+    get_best_match(guess, library).map_or(false, |best| best == sound)
+}
+
+async fn run_quiz(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let store_lock = get_quiz_store(ctx).await?;
+
+    loop {
+        // Gives players a moment to get ready, and to read the last round's results
+        tokio::time::sleep(ROUND_PAUSE).await;
+
+        let (channel_id, started_by, sound, round, rounds, notify) = {
+            let mut games = store_lock.lock().await;
+            let game = match games.get_mut(&guild_id) {
+                Some(game) => game,
+                None => return Ok(()),
+            };
+            let sound = match game.sounds.pop() {
+                Some(sound) if !game.stopped => sound,
+                _ => break,
+            };
+
+            game.round += 1;
+            game.current = Some(Round {
+                sound: sound.clone(),
+                started_at: Instant::now(),
+            });
+            (
+                game.channel_id,
+                game.started_by,
+                sound,
+                game.round,
+                game.rounds,
+                game.notify.clone(),
+            )
+        };
+
+        let announcement = format!("🎵 Round {}/{}: what's this sound?", round, rounds);
+        check_msg(channel_id.say(&ctx.http, announcement).await);
+
+        match get_sound_files()?.get(&sound) {
+            Some(sound_file) => {
+                let origin = PlayOrigin::new(started_by, PlayInterface::Quiz);
+                if let Err(err) =
+                    play_sound(ctx, guild_id, sound_file, origin, &AudioOptions::default()).await
+                {
+                    error!("Error playing quiz sound: {}", err);
+                }
+            }
+            None => error!("Quiz sound {} disappeared from the library", sound),
+        }
+
+        let _ = timeout(ROUND_TIMEOUT, notify.notified()).await;
+
+        let unsolved = {
+            let mut games = store_lock.lock().await;
+            games
+                .get_mut(&guild_id)
+                .and_then(|game| game.current.take())
+        };
+        if let Some(round) = unsolved {
+            let message = format!("⌛ Nobody got it, it was **{}**.", round.sound);
+            check_msg(channel_id.say(&ctx.http, message).await);
+        }
+    }
+
+    finish_quiz(ctx, guild_id).await
+}
+
+/// Announces the results and adds them to the guild's scoreboard
+async fn finish_quiz(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    let game = {
+        let store_lock = get_quiz_store(ctx).await?;
+        let mut games = store_lock.lock().await;
+        match games.remove(&guild_id) {
+            Some(game) => game,
+            None => return Ok(()),
+        }
+    };
+
+    let mut ranking: Vec<(UserId, u64)> = game.scores.into_iter().collect();
+    ranking.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+
+    let mut message = if game.stopped {
+        String::from("🏁 The quiz was stopped.")
+    } else {
+        String::from("🏁 The quiz is over!")
+    };
+    if ranking.is_empty() {
+        message.push_str(" Nobody scored any points.");
+    } else {
+        message.push('\n');
+        for (place, (user_id, points)) in ranking.iter().enumerate() {
+            message.push_str(&format!(
+                "{}. <@{}>: {} points\n",
+                place + 1,
+                user_id,
+                points
+            ));
+        }
+    }
+    check_msg(game.channel_id.say(&ctx.http, message).await);
+
+    if ranking.is_empty() {
+        return Ok(());
+    }
+
+    let scores_lock = get_scores_store(ctx).await?;
+    let mut scores = scores_lock.lock().await;
+    let guild_scores = scores.guilds.entry(*guild_id.as_u64()).or_default();
+    for (user_id, points) in ranking {
+        *guild_scores.entry(*user_id.as_u64()).or_default() += points;
+    }
+
+    persistence::save(QUIZ_SCORES_STORE_NAME, &*scores)
+}
+
+/// Returns the guild's best quiz players, most points first
+pub async fn get_scoreboard(ctx: &Context, guild_id: GuildId) -> Result<Vec<(UserId, u64)>> {
+    let scores_lock = get_scores_store(ctx).await?;
+    let scores = scores_lock.lock().await;
+
+    let mut scoreboard: Vec<(UserId, u64)> = scores
+        .guilds
+        .get(guild_id.as_u64())
+        .map(|scores| {
+            scores
+                .iter()
+                .map(|(user_id, points)| (UserId(*user_id), *points))
+                .collect()
+        })
+        .unwrap_or_default();
+    scoreboard.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+    scoreboard.truncate(SCOREBOARD_SIZE);
+
+    Ok(scoreboard)
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use serenity::{client::Context, model::id::GuildId, prelude::TypeMapKey};
+
+use super::{
+    config::RandomConfig,
+    error::handle_error,
+    history::{get_history, PlayedMedia},
+    sound_files::get_sound_files,
+};
+
+pub struct RandomStore;
+
+impl TypeMapKey for RandomStore {
+    type Value = RandomConfig;
+}
+
+/// Picks a random sound, optionally restricted to sounds whose category matches the filter
+/// or whose name contains it. Sounds among the guild's most recent plays are avoided as long as
+/// other candidates are left. Returns None if no sound matches the filter.
+pub async fn pick_random_sound(
+    ctx: &Context,
+    guild_id: GuildId,
+    filter: Option<&str>,
+) -> Result<Option<String>> {
+    let filter = filter.map(|f| f.to_lowercase());
+    let candidates: Vec<String> = get_sound_files()?
+        .into_iter()
+        .filter(|(name, file)| match &filter {
+            Some(filter) => {
+                file.category
+                    .as_ref()
+                    .map_or(false, |category| category.to_lowercase() == *filter)
+                    || name.to_lowercase().contains(filter.as_str())
+            }
+            None => true,
+        })
+        .map(|(name, _)| name)
+        .collect();
+
+    let avoid_repeats = ctx
+        .data
+        .read()
+        .await
+        .get::<RandomStore>()
+        .map(|config| config.avoid_repeats)
+        .ok_or_else(|| handle_error("Unable to get random config".to_string()))?;
+
+    let recent: Vec<String> = get_history(ctx, guild_id)
+        .await?
+        .into_iter()
+        .take(avoid_repeats)
+        .filter_map(|entry| match entry.media {
+            PlayedMedia::Sound(name) => Some(name),
+            PlayedMedia::Url(_) => None,
+        })
+        .collect();
+
+    let fresh: Vec<&String> = candidates
+        .iter()
+        .filter(|name| !recent.contains(name))
+        .collect();
+
+    let pick = if fresh.is_empty() {
+        candidates.choose(&mut rand::thread_rng()).cloned()
+    } else {
+        fresh
+            .choose(&mut rand::thread_rng())
+            .map(|name| (*name).clone())
+    };
+
+    Ok(pick)
+}
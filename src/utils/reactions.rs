@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::{
+        channel::ReactionType,
+        id::{GuildId, UserId},
+    },
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{error::handle_error, persistence};
+
+pub const REACTIONS_STORE_NAME: &str = "reactions";
+
+pub struct ReactionsStore;
+
+impl TypeMapKey for ReactionsStore {
+    type Value = Arc<Mutex<Reactions>>;
+}
+
+/// Sounds played when someone reacts with an emoji, per guild and keyed by `emoji_key`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Reactions {
+    pub guilds: HashMap<u64, HashMap<String, ReactionSound>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReactionSound {
+    /// The emoji as typed, to show it in listings
+    pub emoji: String,
+    pub sound: String,
+    pub created_by: u64,
+}
+
+// Clients don't agree on whether to send the emoji variation selector
+const VARIATION_SELECTOR: char = '\u{FE0F}';
+
+/// Identifies an emoji the same way whether it was typed or reacted with.
+/// Custom emojis are identified by their ID, since they can be renamed.
+pub fn emoji_key(emoji: &ReactionType) -> Option<String> {
+    match emoji {
+        ReactionType::Unicode(emoji) => Some(emoji.replace(VARIATION_SELECTOR, "")),
+        ReactionType::Custom { id, .. } => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Parses an emoji typed into a command, either unicode or a custom emoji like `<:pascal:123>`
+pub fn parse_emoji(text: &str) -> std::result::Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Please give me an emoji.".to_string());
+    }
+
+    if text.starts_with('<') && text.ends_with('>') {
+        return text
+            .trim_end_matches('>')
+            .rsplit(':')
+            .next()
+            .and_then(|id| id.parse::<u64>().ok())
+            .map(|id| id.to_string())
+            .ok_or_else(|| format!("**{}** is not an emoji I can use.", text));
+    }
+
+    // Unicode emojis never contain whitespace or letters, which catches most typos
+    if text
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_ascii_alphabetic())
+    {
+        return Err(format!("**{}** is not an emoji.", text));
+    }
+
+    Ok(text.replace(VARIATION_SELECTOR, ""))
+}
+
+async fn get_reactions_store(ctx: &Context) -> Result<Arc<Mutex<Reactions>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<ReactionsStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get reactions store".to_string()))
+}
+
+/// Returns the sound mapped to the emoji, if any
+pub async fn get_reaction_sound(
+    ctx: &Context,
+    guild_id: GuildId,
+    key: &str,
+) -> Result<Option<String>> {
+    let store_lock = get_reactions_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store
+        .guilds
+        .get(guild_id.as_u64())
+        .and_then(|reactions| reactions.get(key))
+        .map(|reaction| reaction.sound.clone()))
+}
+
+/// Returns the guild's reaction sounds sorted by sound
+pub async fn get_reaction_sounds(ctx: &Context, guild_id: GuildId) -> Result<Vec<ReactionSound>> {
+    let store_lock = get_reactions_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    let mut reactions: Vec<ReactionSound> = store
+        .guilds
+        .get(guild_id.as_u64())
+        .map(|reactions| reactions.values().cloned().collect())
+        .unwrap_or_default();
+    reactions.sort_unstable_by(|a, b| a.sound.cmp(&b.sound));
+
+    Ok(reactions)
+}
+
+/// Maps the emoji to a sound, replacing its previous sound
+pub async fn save_reaction_sound(
+    ctx: &Context,
+    guild_id: GuildId,
+    key: String,
+    emoji: &str,
+    sound: &str,
+    created_by: UserId,
+) -> Result<()> {
+    let store_lock = get_reactions_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    store.guilds.entry(*guild_id.as_u64()).or_default().insert(
+        key,
+        ReactionSound {
+            emoji: emoji.trim().to_owned(),
+            sound: sound.to_owned(),
+            created_by: *created_by.as_u64(),
+        },
+    );
+
+    persistence::save(REACTIONS_STORE_NAME, &*store)
+}
+
+/// Returns false if the emoji had no sound
+pub async fn delete_reaction_sound(ctx: &Context, guild_id: GuildId, key: &str) -> Result<bool> {
+    let store_lock = get_reactions_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let removed = store
+        .guilds
+        .get_mut(guild_id.as_u64())
+        .and_then(|reactions| reactions.remove(key))
+        .is_some();
+    if removed {
+        persistence::save(REACTIONS_STORE_NAME, &*store)?;
+    }
+
+    Ok(removed)
+}
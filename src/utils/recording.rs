@@ -0,0 +1,455 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::info;
+use serenity::{
+    async_trait,
+    client::Context,
+    model::id::{ChannelId, GuildId},
+    prelude::{Mutex, TypeMapKey},
+};
+use songbird::{
+    driver::DecodeMode, Config as VoiceConfig, CoreEvent, Event, EventContext,
+    EventHandler as VoiceEventHandler,
+};
+
+use super::{
+    config::RecordingConfig,
+    effects::{render_to_file, AudioOptions},
+    error::{check_msg, handle_error},
+    guild_settings::get_guild_settings,
+    sound_files::{get_sound_files, new_sound_path, validate_sound_name},
+};
+
+const CLIPS_DIR: &str = "./data/clips";
+/// Most seconds of audio kept per guild, whatever the config says
+pub const MAX_BUFFER_SECS: u64 = 120;
+const SAMPLE_RATE: u32 = 48_000;
+const CHANNELS: u16 = 2;
+/// Songbird decodes voice packets into 20ms frames of interleaved stereo samples
+const FRAME_MILLIS: u128 = 20;
+const FRAMES_PER_SEC: u64 = 50;
+const FRAME_SAMPLES: usize = (SAMPLE_RATE as usize / FRAMES_PER_SEC as usize) * CHANNELS as usize;
+/// RTP timestamps count samples per channel
+const FRAME_TICKS: u32 = SAMPLE_RATE / FRAMES_PER_SEC as u32;
+/// How far a speaker's packets may stray from the clock before they are placed anew
+const MAX_DRIFT_FRAMES: u64 = FRAMES_PER_SEC;
+
+/// Audio buffers of the guilds Pascal is recording in
+pub struct RecordingStore;
+
+impl TypeMapKey for RecordingStore {
+    type Value = Arc<Mutex<HashMap<GuildId, Arc<Mutex<ClipBuffer>>>>>;
+}
+
+pub struct RecordingConfigStore;
+
+impl TypeMapKey for RecordingConfigStore {
+    type Value = RecordingConfig;
+}
+
+/// Rolling buffer of a voice channel's audio, every speaker mixed together.
+/// Packets are placed by their RTP timestamp, so jitter doesn't stretch or squash speech.
+pub struct ClipBuffer {
+    channel_id: ChannelId,
+    started_at: Instant,
+    /// Number of the oldest frame in `frames`, counted from `started_at`
+    first_frame: u64,
+    /// Empty frames are silence, so quiet channels take up next to no memory
+    frames: VecDeque<Vec<i16>>,
+    max_frames: usize,
+    /// RTP timestamp and frame of each speaker's (SSRC's) first packet
+    speakers: HashMap<u32, (u32, u64)>,
+    /// Set once recording stops, so the receiver unregisters itself
+    stopped: bool,
+}
+
+impl ClipBuffer {
+    fn new(channel_id: ChannelId, buffer_secs: u64) -> Self {
+        ClipBuffer {
+            channel_id,
+            started_at: Instant::now(),
+            first_frame: 0,
+            frames: VecDeque::new(),
+            max_frames: (buffer_secs * FRAMES_PER_SEC) as usize,
+            speakers: HashMap::new(),
+            stopped: false,
+        }
+    }
+
+    fn current_frame(&self) -> u64 {
+        (self.started_at.elapsed().as_millis() / FRAME_MILLIS) as u64
+    }
+
+    /// Drops the frames that are too old to be kept as of the given frame
+    fn advance(&mut self, frame: u64) {
+        let first_frame = (frame + 1).saturating_sub(self.max_frames as u64);
+        if first_frame > self.first_frame {
+            let expired = ((first_frame - self.first_frame) as usize).min(self.frames.len());
+            self.frames.drain(..expired);
+            self.first_frame = first_frame;
+        }
+    }
+
+    fn push(&mut self, ssrc: u32, timestamp: u32, audio: &[i16]) {
+        let now = self.current_frame();
+        self.advance(now);
+        let frame = self.place(ssrc, timestamp, now);
+        if frame < self.first_frame {
+            return;
+        }
+
+        let index = (frame - self.first_frame) as usize;
+        while self.frames.len() <= index {
+            self.frames.push_back(Vec::new());
+        }
+
+        let mixed = &mut self.frames[index];
+        if mixed.len() < audio.len() {
+            mixed.resize(audio.len(), 0);
+        }
+        for (mixed, sample) in mixed.iter_mut().zip(audio) {
+            *mixed = mixed.saturating_add(*sample);
+        }
+    }
+
+    /// Returns the frame a speaker's packet belongs in. A speaker whose timestamps jump
+    /// or drift too far from the clock is placed in the current frame from then on.
+    fn place(&mut self, ssrc: u32, timestamp: u32, now: u64) -> u64 {
+        if let Some(&(base_timestamp, base_frame)) = self.speakers.get(&ssrc) {
+            // Late packets have timestamps just behind the first one
+            let offset = timestamp.wrapping_sub(base_timestamp) as i32 as i64 / FRAME_TICKS as i64;
+            let frame = (base_frame as i64 + offset).max(0) as u64;
+            if frame.max(now) - frame.min(now) <= MAX_DRIFT_FRAMES {
+                return frame;
+            }
+        }
+
+        self.speakers.insert(ssrc, (timestamp, now));
+        now
+    }
+
+    /// Returns the last seconds of audio as interleaved stereo samples, None if nobody spoke
+    fn last_seconds(&mut self, secs: u64) -> Option<Vec<i16>> {
+        let now = self.current_frame();
+        self.advance(now);
+
+        let start = now
+            .saturating_sub(secs * FRAMES_PER_SEC)
+            .max(self.first_frame);
+        let mut samples = Vec::with_capacity((now - start + 1) as usize * FRAME_SAMPLES);
+        let mut heard = false;
+        for frame in start..=now {
+            let end = samples.len() + FRAME_SAMPLES;
+            if let Some(mixed) = self.frames.get((frame - self.first_frame) as usize) {
+                heard |= !mixed.is_empty();
+                samples.extend(mixed.iter().take(FRAME_SAMPLES));
+            }
+            samples.resize(end, 0);
+        }
+
+        if heard {
+            Some(samples)
+        } else {
+            None
+        }
+    }
+
+    fn reset(&mut self, channel_id: ChannelId) {
+        self.channel_id = channel_id;
+        self.started_at = Instant::now();
+        self.first_frame = 0;
+        self.frames.clear();
+        self.speakers.clear();
+    }
+
+    fn stop(&mut self) {
+        self.stopped = true;
+        self.frames.clear();
+        self.speakers.clear();
+    }
+}
+
+struct VoiceReceiver {
+    buffer: Arc<Mutex<ClipBuffer>>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for VoiceReceiver {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::VoicePacket(data) = ctx {
+            let mut buffer = self.buffer.lock().await;
+            if buffer.stopped {
+                return Some(Event::Cancel);
+            }
+            if let Some(audio) = data.audio {
+                buffer.push(data.packet.ssrc, data.packet.timestamp.into(), audio);
+            }
+        }
+
+        None
+    }
+}
+
+async fn get_recording_store(
+    ctx: &Context,
+) -> Result<Arc<Mutex<HashMap<GuildId, Arc<Mutex<ClipBuffer>>>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<RecordingStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get recording store".to_string()))
+}
+
+/// Returns how many seconds of audio are kept, capped at `MAX_BUFFER_SECS`
+pub async fn get_buffer_secs(ctx: &Context) -> Result<u64> {
+    let config = ctx
+        .data
+        .read()
+        .await
+        .get::<RecordingConfigStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get recording config".to_string()))?;
+
+    Ok(config.buffer_secs.clamp(1, MAX_BUFFER_SECS))
+}
+
+/// Returns the voice channel Pascal is recording in the guild, if any
+pub async fn get_recorded_channel(ctx: &Context, guild_id: GuildId) -> Result<Option<ChannelId>> {
+    let store_lock = get_recording_store(ctx).await?;
+    let buffer_lock = store_lock.lock().await.get(&guild_id).cloned();
+
+    Ok(match buffer_lock {
+        Some(buffer_lock) => Some(buffer_lock.lock().await.channel_id),
+        None => None,
+    })
+}
+
+/// Starts keeping the last seconds of the voice channel's audio if the guild opted in.
+/// The guild's recording channel is told every time recording starts in a voice channel.
+pub async fn start_recording(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<()> {
+    let announcement_channel = match get_guild_settings(ctx, guild_id).await?.recording_channel {
+        Some(announcement_channel) => ChannelId(announcement_channel),
+        None => return Ok(()),
+    };
+    let buffer_secs = get_buffer_secs(ctx).await?;
+
+    let store_lock = get_recording_store(ctx).await?;
+    let mut recordings = store_lock.lock().await;
+    if let Some(buffer_lock) = recordings.get(&guild_id).cloned() {
+        let mut buffer = buffer_lock.lock().await;
+        if buffer.channel_id == channel_id {
+            return Ok(());
+        }
+        // Audio of the previous channel must not end up in clips of this one
+        buffer.reset(channel_id);
+    } else {
+        let manager = songbird::get(ctx)
+            .await
+            .ok_or_else(|| handle_error("Error fetching Songbird client".to_string()))?;
+        let call_lock = match manager.get(guild_id) {
+            Some(call_lock) => call_lock,
+            None => return Ok(()),
+        };
+
+        let buffer = Arc::new(Mutex::new(ClipBuffer::new(channel_id, buffer_secs)));
+        let mut call = call_lock.lock().await;
+        // Decoding everyone's voice costs CPU, so only calls that are recorded do it
+        call.set_config(VoiceConfig::default().decode_mode(DecodeMode::Decode));
+        call.add_global_event(
+            CoreEvent::VoicePacket.into(),
+            VoiceReceiver {
+                buffer: buffer.clone(),
+            },
+        );
+        drop(call);
+        recordings.insert(guild_id, buffer);
+    }
+    drop(recordings);
+
+    info!("Recording {} in guild {}", channel_id, guild_id);
+    let message = format!(
+        "🔴 Pascal is recording <#{}> and keeps the last {} seconds, so anyone can save them as a sound with `/clip`. Admins can turn this off with `/recording off`.",
+        channel_id, buffer_secs
+    );
+    check_msg(announcement_channel.say(&ctx.http, message).await);
+
+    Ok(())
+}
+
+/// Stops recording in the guild and throws the buffered audio away.
+/// Returns false if Pascal wasn't recording.
+pub async fn stop_recording(ctx: &Context, guild_id: GuildId) -> Result<bool> {
+    let store_lock = get_recording_store(ctx).await?;
+    let buffer_lock = store_lock.lock().await.remove(&guild_id);
+
+    Ok(match buffer_lock {
+        Some(buffer_lock) => {
+            buffer_lock.lock().await.stop();
+
+            let manager = songbird::get(ctx)
+                .await
+                .ok_or_else(|| handle_error("Error fetching Songbird client".to_string()))?;
+            if let Some(call_lock) = manager.get(guild_id) {
+                call_lock.lock().await.set_config(VoiceConfig::default());
+            }
+
+            info!("Stopped recording in guild {}", guild_id);
+            true
+        }
+        None => false,
+    })
+}
+
+/// Keeps recording in line with the voice channel Pascal itself is in,
+/// e.g. when someone moves or disconnects it
+pub async fn update_recording(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: Option<ChannelId>,
+) -> Result<()> {
+    match channel_id {
+        Some(channel_id) => start_recording(ctx, guild_id, channel_id).await,
+        None => stop_recording(ctx, guild_id).await.map(|_| ()),
+    }
+}
+
+/// Saves the last seconds of the guild's recording as a new library sound.
+/// The inner error describes why the clip can't be saved.
+pub async fn save_recording(
+    ctx: &Context,
+    guild_id: GuildId,
+    secs: u64,
+    name: &str,
+) -> Result<std::result::Result<(), String>> {
+    if let Err(err) = validate_sound_name(name) {
+        return Ok(Err(err));
+    }
+    if get_sound_files()?.contains_key(name) {
+        return Ok(Err(format!("There already is a sound called **{}**", name)));
+    }
+
+    let store_lock = get_recording_store(ctx).await?;
+    let buffer_lock = store_lock.lock().await.get(&guild_id).cloned();
+    let buffer_lock =
+        match buffer_lock {
+            Some(buffer_lock) => buffer_lock,
+            None => return Ok(Err(
+                "Pascal isn't recording here. Admins can turn recording on with `/recording on`."
+                    .to_string(),
+            )),
+        };
+    let secs = secs.clamp(1, MAX_BUFFER_SECS);
+    let samples = buffer_lock.lock().await.last_seconds(secs);
+    let samples = match samples {
+        Some(samples) => samples,
+        None => {
+            return Ok(Err(format!(
+                "Nobody said anything in the last {} seconds.",
+                secs
+            )))
+        }
+    };
+
+    fs::create_dir_all(CLIPS_DIR).with_context(|| "Error creating clips directory")?;
+    let recording_path = PathBuf::from(CLIPS_DIR).join(format!("{}-{}.wav", guild_id, name));
+    write_wav(&recording_path, &samples)?;
+
+    let rendered = render_to_file(
+        &recording_path,
+        &AudioOptions::default(),
+        &new_sound_path(name),
+    )
+    .await;
+    let _ = fs::remove_file(&recording_path);
+    rendered?;
+
+    Ok(Ok(()))
+}
+
+/// Writes interleaved stereo samples as a 16 bit PCM WAV file
+fn write_wav(path: &Path, samples: &[i16]) -> Result<()> {
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = CHANNELS * 2;
+
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&CHANNELS.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    fs::write(path, bytes).with_context(|| format!("Error writing recording to {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_buffer() -> ClipBuffer {
+        ClipBuffer::new(ChannelId(1), 10)
+    }
+
+    #[test]
+    fn places_packets_by_timestamp() {
+        let mut buffer = new_buffer();
+        assert_eq!(buffer.place(1, 1000, 5), 5);
+        // A burst of packets arriving at once still lands in consecutive frames
+        assert_eq!(buffer.place(1, 1000 + FRAME_TICKS, 5), 6);
+        assert_eq!(buffer.place(1, 1000 + 2 * FRAME_TICKS, 5), 7);
+        // A packet arriving late goes back where it belongs
+        assert_eq!(buffer.place(1, 1000 + 3 * FRAME_TICKS, 12), 8);
+        assert_eq!(buffer.place(1, 1000 - FRAME_TICKS, 12), 4);
+    }
+
+    #[test]
+    fn places_speakers_independently() {
+        let mut buffer = new_buffer();
+        assert_eq!(buffer.place(1, 1000, 5), 5);
+        assert_eq!(buffer.place(2, 9_000_000, 7), 7);
+        assert_eq!(buffer.place(1, 1000 + FRAME_TICKS, 7), 6);
+        assert_eq!(buffer.place(2, 9_000_000 + FRAME_TICKS, 7), 8);
+    }
+
+    #[test]
+    fn follows_wrapping_timestamps() {
+        let mut buffer = new_buffer();
+        assert_eq!(buffer.place(1, u32::MAX - 100, 5), 5);
+        assert_eq!(
+            buffer.place(1, (u32::MAX - 100).wrapping_add(FRAME_TICKS), 5),
+            6
+        );
+    }
+
+    #[test]
+    fn places_drifting_speakers_anew() {
+        let mut buffer = new_buffer();
+        assert_eq!(buffer.place(1, 1000, 5), 5);
+        // Timestamps that jumped far ahead of the clock
+        assert_eq!(buffer.place(1, 1000 + 1000 * FRAME_TICKS, 6), 6);
+        assert_eq!(buffer.place(1, 1000 + 1001 * FRAME_TICKS, 6), 7);
+        // A speaker silent for longer than the timestamps account for
+        assert_eq!(buffer.place(1, 1000 + 1002 * FRAME_TICKS, 500), 500);
+    }
+}
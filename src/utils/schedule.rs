@@ -0,0 +1,627 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{
+    discord::{join_channel, play_sound},
+    effects::AudioOptions,
+    error::handle_error,
+    history::{PlayInterface, PlayOrigin},
+    idle::has_listeners,
+    persistence,
+    sound_files::get_sound_files,
+};
+
+pub const SCHEDULES_STORE_NAME: &str = "schedules";
+
+pub const MAX_SCHEDULES_PER_GUILD: usize = 25;
+
+// Often enough to never skip a minute
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(20);
+// One-off sounds missed by more than this while Pascal was offline are dropped
+const MISSED_GRACE_SECS: i64 = 5 * 60;
+
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+pub struct SchedulesStore;
+
+impl TypeMapKey for SchedulesStore {
+    type Value = Arc<Mutex<Schedules>>;
+}
+
+/// Sounds played in a voice channel at a set time, per guild
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Schedules {
+    pub next_id: u64,
+    pub guilds: HashMap<u64, Vec<ScheduledSound>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduledSound {
+    pub id: u64,
+    pub channel_id: u64,
+    pub sound: String,
+    pub when: ScheduleTime,
+    pub created_by: u64,
+    /// Minutes since the epoch of the last time the sound was played
+    pub last_run_minute: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum ScheduleTime {
+    /// Plays once at the given unix timestamp
+    Once { at: i64 },
+    /// Plays whenever the cron expression matches
+    Recurring { cron: String },
+}
+
+impl ScheduleTime {
+    /// Parses a cron expression like `0 17 * * fri` or a single point in time,
+    /// either `17:00` for the next time it's 17:00 or `2021-12-24 18:00`
+    pub fn parse(text: &str, now: DateTime<Local>) -> std::result::Result<Self, String> {
+        let text = text.trim();
+
+        let cron = match text {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            _ => text,
+        };
+        if cron.split_whitespace().count() == 5 {
+            CronSchedule::parse(cron)?;
+            return Ok(ScheduleTime::Recurring {
+                cron: cron.to_owned(),
+            });
+        }
+
+        let at = if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M") {
+            let today = now.date_naive().and_time(time);
+            let at = local_time(today)?;
+            if at > now {
+                at
+            } else {
+                local_time(today + chrono::Duration::days(1))?
+            }
+        } else if let Ok(date_time) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+            local_time(date_time)?
+        } else {
+            return Err(format!(
+                "I don't understand **{}**. Use a time like 17:00 or 2021-12-24 18:00, or a cron expression like `0 17 * * fri`.",
+                text
+            ));
+        };
+        if at <= now {
+            return Err("That time has already passed.".to_string());
+        }
+
+        Ok(ScheduleTime::Once { at: at.timestamp() })
+    }
+}
+
+impl std::fmt::Display for ScheduleTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // schedules.json could hold a timestamp chrono can't represent
+            ScheduleTime::Once { at } => match Local.timestamp_opt(*at, 0).single() {
+                Some(time) => write!(f, "{}", time.format("%Y-%m-%d %H:%M")),
+                None => write!(f, "unix time {}", at),
+            },
+            ScheduleTime::Recurring { cron } => write!(f, "`{}`", cron),
+        }
+    }
+}
+
+fn local_time(date_time: NaiveDateTime) -> std::result::Result<DateTime<Local>, String> {
+    Local
+        .from_local_datetime(&date_time)
+        .earliest()
+        .ok_or_else(|| "That time doesn't exist here.".to_string())
+}
+
+/// Cron expression with the fields minute, hour, day of month, month and day of week
+struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // Like in cron, a sound plays on either a matching day or weekday if both are restricted
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl CronSchedule {
+    fn parse(expression: &str) -> std::result::Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(
+                "A cron expression needs 5 fields: minute hour day month weekday".to_string(),
+            );
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7, &WEEKDAY_NAMES)?;
+        // Both 0 and 7 are sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, &MONTH_NAMES)?,
+            weekdays,
+            days_restricted: !fields[2].starts_with('*'),
+            weekdays_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches(&self, time: &DateTime<Local>) -> bool {
+        let is_set = |bits: u64, value: u32| bits & (1 << value) != 0;
+
+        let day = is_set(self.days, time.day());
+        let weekday = is_set(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        };
+
+        is_set(self.minutes, time.minute())
+            && is_set(self.hours, time.hour())
+            && is_set(self.months, time.month())
+            && day_matches
+    }
+}
+
+/// Parses a cron field like `*/15`, `1-5`, `mon,fri` into a bit set of the allowed values
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+) -> std::result::Result<u64, String> {
+    let invalid = || format!("**{}** is not a valid cron field.", field);
+    let parse_value = |value: &str| -> Option<u32> {
+        value.parse::<u32>().ok().or_else(|| {
+            names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(value))
+                .map(|index| min + index as u32)
+        })
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().map_err(|_| invalid())?)),
+            None => (part, None),
+        };
+        let step = step.unwrap_or(1);
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (low, high) = if range == "*" {
+            (min, max)
+        } else if let Some((low, high)) = range.split_once('-') {
+            (
+                parse_value(low).ok_or_else(invalid)?,
+                parse_value(high).ok_or_else(invalid)?,
+            )
+        } else {
+            let value = parse_value(range).ok_or_else(invalid)?;
+            // `5/10` means every 10 starting at 5
+            if part.contains('/') {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+        if low < min || high > max || low > high {
+            return Err(invalid());
+        }
+
+        for value in (low..=high).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+async fn get_schedules_store(ctx: &Context) -> Result<Arc<Mutex<Schedules>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<SchedulesStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get schedules store".to_string()))
+}
+
+/// Returns the guild's scheduled sounds in the order they were added
+pub async fn get_schedules(ctx: &Context, guild_id: GuildId) -> Result<Vec<ScheduledSound>> {
+    let store_lock = get_schedules_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    Ok(store
+        .guilds
+        .get(guild_id.as_u64())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Schedules a sound, returning its ID or the reason the guild can't have more schedules
+pub async fn add_schedule(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    sound: &str,
+    when: ScheduleTime,
+    created_by: UserId,
+) -> Result<std::result::Result<u64, String>> {
+    let store_lock = get_schedules_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let id = store.next_id + 1;
+    let schedules = store.guilds.entry(*guild_id.as_u64()).or_default();
+    if schedules.len() >= MAX_SCHEDULES_PER_GUILD {
+        return Ok(Err(format!(
+            "There can't be more than {} scheduled sounds, please remove one first.",
+            MAX_SCHEDULES_PER_GUILD
+        )));
+    }
+    schedules.push(ScheduledSound {
+        id,
+        channel_id: *channel_id.as_u64(),
+        sound: sound.to_owned(),
+        when,
+        created_by: *created_by.as_u64(),
+        last_run_minute: None,
+    });
+    store.next_id = id;
+
+    persistence::save(SCHEDULES_STORE_NAME, &*store)?;
+
+    Ok(Ok(id))
+}
+
+/// Returns false if the guild had no scheduled sound with that ID
+pub async fn remove_schedule(ctx: &Context, guild_id: GuildId, id: u64) -> Result<bool> {
+    let store_lock = get_schedules_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let removed = match store.guilds.get_mut(guild_id.as_u64()) {
+        Some(schedules) => {
+            let count = schedules.len();
+            schedules.retain(|schedule| schedule.id != id);
+            schedules.len() != count
+        }
+        None => false,
+    };
+    if removed {
+        persistence::save(SCHEDULES_STORE_NAME, &*store)?;
+    }
+
+    Ok(removed)
+}
+
+/// Spawns a background task that plays scheduled sounds when they're due
+pub fn start_scheduler(ctx: Context) {
+    // `ready` fires again on every reconnect, but one scheduler is enough
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = play_due_schedules(&ctx).await {
+                error!("Error playing scheduled sounds: {}", err);
+            }
+        }
+    });
+}
+
+/// Collects the scheduled sounds due now, marking them as played
+async fn take_due_schedules(ctx: &Context) -> Result<Vec<(GuildId, ScheduledSound)>> {
+    let store_lock = get_schedules_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let now = Local::now();
+    let minute = now.timestamp() / 60;
+    let mut due = Vec::new();
+    let mut changed = false;
+
+    for (guild_id, schedules) in store.guilds.iter_mut() {
+        let guild_id = GuildId(*guild_id);
+
+        schedules.retain(|schedule| match &schedule.when {
+            ScheduleTime::Once { at } if *at <= now.timestamp() => {
+                changed = true;
+                if now.timestamp() - at <= MISSED_GRACE_SECS {
+                    due.push((guild_id, schedule.clone()));
+                } else {
+                    info!(
+                        "Dropping scheduled sound {} in guild {}, it was missed",
+                        schedule.id, guild_id
+                    );
+                }
+                false
+            }
+            _ => true,
+        });
+
+        for schedule in schedules.iter_mut() {
+            let cron = match &schedule.when {
+                ScheduleTime::Recurring { cron } => cron,
+                ScheduleTime::Once { .. } => continue,
+            };
+            if schedule.last_run_minute == Some(minute) {
+                continue;
+            }
+
+            match CronSchedule::parse(cron) {
+                Ok(cron) if cron.matches(&now) => {
+                    schedule.last_run_minute = Some(minute);
+                    changed = true;
+                    due.push((guild_id, schedule.clone()));
+                }
+                Ok(_) => (),
+                Err(err) => error!(
+                    "Invalid cron expression of schedule {}: {}",
+                    schedule.id, err
+                ),
+            }
+        }
+    }
+
+    if changed {
+        persistence::save(SCHEDULES_STORE_NAME, &*store)?;
+    }
+
+    Ok(due)
+}
+
+async fn play_due_schedules(ctx: &Context) -> Result<()> {
+    let due = take_due_schedules(ctx).await?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let sound_files = get_sound_files()?;
+    for (guild_id, schedule) in due {
+        let channel_id = ChannelId(schedule.channel_id);
+
+        // A chime nobody hears isn't worth joining for
+        if !has_listeners(ctx, guild_id, channel_id).await {
+            info!(
+                "Skipping scheduled sound {} in guild {}, nobody is listening",
+                schedule.id, guild_id
+            );
+            continue;
+        }
+
+        let sound_file = match sound_files.get(&schedule.sound) {
+            Some(sound_file) => sound_file,
+            None => {
+                error!(
+                    "Scheduled sound {} in guild {} plays missing file {}",
+                    schedule.id, guild_id, schedule.sound
+                );
+                continue;
+            }
+        };
+
+        if let Err(err) = join_channel(ctx, guild_id, channel_id).await {
+            error!("Error joining voice channel: {}", err);
+            continue;
+        }
+
+        let origin = PlayOrigin::new(UserId(schedule.created_by), PlayInterface::Schedule);
+        if let Err(err) =
+            play_sound(ctx, guild_id, sound_file, origin, &AudioOptions::default()).await
+        {
+            error!("Error playing scheduled sound: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Local> {
+        local_time(NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()).unwrap()
+    }
+
+    fn values(bits: u64) -> Vec<u32> {
+        (0..64).filter(|value| bits & (1 << value) != 0).collect()
+    }
+
+    #[test]
+    fn parses_values_ranges_and_lists() {
+        assert_eq!(values(parse_field("5", 0, 59, &[]).unwrap()), vec![5]);
+        assert_eq!(
+            values(parse_field("1-4", 0, 59, &[]).unwrap()),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(
+            values(parse_field("1,3,10-11", 0, 59, &[]).unwrap()),
+            vec![1, 3, 10, 11]
+        );
+        assert_eq!(
+            values(parse_field("*", 1, 12, &[]).unwrap()),
+            (1..=12).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parses_steps() {
+        assert_eq!(
+            values(parse_field("*/15", 0, 59, &[]).unwrap()),
+            vec![0, 15, 30, 45]
+        );
+        assert_eq!(
+            values(parse_field("10-20/5", 0, 59, &[]).unwrap()),
+            vec![10, 15, 20]
+        );
+        assert_eq!(
+            values(parse_field("5/20", 0, 59, &[]).unwrap()),
+            vec![5, 25, 45]
+        );
+        assert_eq!(values(parse_field("*/7", 1, 12, &[]).unwrap()), vec![1, 8]);
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(
+            values(parse_field("mon-fri", 0, 7, &WEEKDAY_NAMES).unwrap()),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            values(parse_field("Jan,DEC", 1, 12, &MONTH_NAMES).unwrap()),
+            vec![1, 12]
+        );
+        assert!(parse_field("mon", 1, 12, &MONTH_NAMES).is_err());
+        assert!(parse_field("monday", 0, 7, &WEEKDAY_NAMES).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        assert!(parse_field("60", 0, 59, &[]).is_err());
+        assert!(parse_field("0", 1, 31, &[]).is_err());
+        assert!(parse_field("5-1", 0, 59, &[]).is_err());
+        assert!(parse_field("*/0", 0, 59, &[]).is_err());
+        assert!(parse_field("1,,2", 0, 59, &[]).is_err());
+        assert!(parse_field("-5", 0, 59, &[]).is_err());
+        assert!(parse_field("99999999999", 0, 59, &[]).is_err());
+        assert!(parse_field("*/99999999999", 0, 59, &[]).is_err());
+        assert!(parse_field("", 0, 59, &[]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("0 17 * *").is_err());
+        assert!(CronSchedule::parse("0 17 * * * *").is_err());
+        assert!(CronSchedule::parse("0 24 * * *").is_err());
+        assert!(CronSchedule::parse("0 17 32 * *").is_err());
+        assert!(CronSchedule::parse("0 17 * 13 *").is_err());
+        assert!(CronSchedule::parse("0 17 * * 8").is_err());
+    }
+
+    #[test]
+    fn matches_times() {
+        // 2021-12-24 is a friday
+        let schedule = CronSchedule::parse("30 17 * * fri").unwrap();
+        assert!(schedule.matches(&at("2021-12-24 17:30")));
+        assert!(!schedule.matches(&at("2021-12-24 17:31")));
+        assert!(!schedule.matches(&at("2021-12-25 17:30")));
+
+        let schedule = CronSchedule::parse("0 9 1 jan *").unwrap();
+        assert!(schedule.matches(&at("2022-01-01 09:00")));
+        assert!(!schedule.matches(&at("2022-02-01 09:00")));
+    }
+
+    #[test]
+    fn weekday_seven_is_sunday() {
+        // 2021-12-26 is a sunday
+        let schedule = CronSchedule::parse("0 12 * * 7").unwrap();
+        assert_eq!(values(schedule.weekdays), vec![0]);
+        assert!(schedule.matches(&at("2021-12-26 12:00")));
+
+        let schedule = CronSchedule::parse("0 12 * * 5-7").unwrap();
+        assert_eq!(values(schedule.weekdays), vec![0, 5, 6]);
+    }
+
+    #[test]
+    fn matches_day_or_weekday_if_both_are_restricted() {
+        // The 1st of the month or any monday
+        let schedule = CronSchedule::parse("0 8 1 * mon").unwrap();
+        assert!(schedule.matches(&at("2021-12-01 08:00")));
+        assert!(schedule.matches(&at("2021-12-06 08:00")));
+        assert!(!schedule.matches(&at("2021-12-07 08:00")));
+
+        // Only one of them restricted means both have to match
+        let schedule = CronSchedule::parse("0 8 1 * *").unwrap();
+        assert!(schedule.matches(&at("2021-12-01 08:00")));
+        assert!(!schedule.matches(&at("2021-12-06 08:00")));
+        let schedule = CronSchedule::parse("0 8 */2 * mon").unwrap();
+        assert!(!schedule.matches(&at("2021-12-01 08:00")));
+        assert!(schedule.matches(&at("2021-12-13 08:00")));
+        assert!(!schedule.matches(&at("2021-12-06 08:00")));
+    }
+
+    #[test]
+    fn parses_schedule_times() {
+        let now = at("2021-12-24 12:00");
+
+        assert!(matches!(
+            ScheduleTime::parse(" 0 17 * * fri ", now),
+            Ok(ScheduleTime::Recurring { cron }) if cron == "0 17 * * fri"
+        ));
+        assert!(matches!(
+            ScheduleTime::parse("@daily", now),
+            Ok(ScheduleTime::Recurring { cron }) if cron == "0 0 * * *"
+        ));
+        assert!(matches!(
+            ScheduleTime::parse("17:00", now),
+            Ok(ScheduleTime::Once { at: time }) if time == at("2021-12-24 17:00").timestamp()
+        ));
+        assert!(matches!(
+            ScheduleTime::parse("11:00", now),
+            Ok(ScheduleTime::Once { at: time }) if time == at("2021-12-25 11:00").timestamp()
+        ));
+        assert!(matches!(
+            ScheduleTime::parse("2021-12-31 23:59", now),
+            Ok(ScheduleTime::Once { at: time }) if time == at("2021-12-31 23:59").timestamp()
+        ));
+    }
+
+    #[test]
+    fn displays_schedule_times() {
+        let time = ScheduleTime::Once {
+            at: at("2021-12-24 17:00").timestamp(),
+        };
+        assert_eq!(time.to_string(), "2021-12-24 17:00");
+
+        let time = ScheduleTime::Once { at: i64::MAX };
+        assert_eq!(time.to_string(), format!("unix time {}", i64::MAX));
+    }
+
+    #[test]
+    fn rejects_invalid_schedule_times() {
+        let now = at("2021-12-24 12:00");
+
+        assert!(ScheduleTime::parse("2021-12-24 11:00", now).is_err());
+        assert!(ScheduleTime::parse("25:00", now).is_err());
+        assert!(ScheduleTime::parse("tomorrow", now).is_err());
+        assert!(ScheduleTime::parse("0 17 * * funday", now).is_err());
+    }
+}
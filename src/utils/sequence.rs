@@ -0,0 +1,237 @@
+use std::{collections::HashMap, sync::Mutex as StdMutex, time::Duration};
+
+use anyhow::Result;
+use log::error;
+use serenity::{
+    async_trait,
+    client::Context,
+    model::id::{ChannelId, GuildId},
+};
+use songbird::{
+    tracks::{PlayMode, TrackHandle},
+    Event, EventContext, EventHandler as VoiceEventHandler, TrackEvent,
+};
+use tokio::sync::oneshot;
+
+use super::{
+    discord::play_sound,
+    effects::AudioOptions,
+    error::check_msg,
+    history::PlayOrigin,
+    sound_files::{get_sound_files, SoundFile},
+};
+
+/// Longest pause a sequence may hold, anything longer is surely a typo
+pub const MAX_PAUSE: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SequenceStep {
+    Sound(String),
+    Pause(Duration),
+}
+
+/// Parses the items of a sequence like `drumroll, 500ms, tada`.
+/// Items that look like a duration (`500ms`, `1.5s`) are pauses, everything else is a sound.
+/// Returns the reason if a pause is longer than `MAX_PAUSE`.
+pub fn parse_steps<S: AsRef<str>>(items: &[S]) -> Result<Vec<SequenceStep>, String> {
+    items
+        .iter()
+        .map(|item| item.as_ref().trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| match parse_pause(item) {
+            Some(pause) if pause > MAX_PAUSE => Err(format!(
+                "Pauses may be {} seconds long at most, **{}** is too long",
+                MAX_PAUSE.as_secs(),
+                item
+            )),
+            Some(pause) => Ok(SequenceStep::Pause(pause)),
+            None => Ok(SequenceStep::Sound(item.to_owned())),
+        })
+        .collect()
+}
+
+/// Parses durations like `500ms` or `1.5s`
+pub fn parse_pause(item: &str) -> Option<Duration> {
+    if let Some(millis) = item.strip_suffix("ms") {
+        return millis.parse::<u64>().ok().map(Duration::from_millis);
+    }
+
+    item.strip_suffix('s')
+        .and_then(|secs| secs.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        // Too long for a duration is too long for a pause as well
+        .map(|secs| Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX))
+}
+
+/// Plays the steps one after another in the background, waiting `gap` between two sounds.
+/// Unknown sounds are reported in the channel and nothing is played.
+/// The sequence is abandoned as soon as one of its sounds gets stopped.
+pub async fn play_sequence(
+    ctx: &Context,
+    channel_id: ChannelId,
+    guild_id: GuildId,
+    steps: Vec<SequenceStep>,
+    gap: Duration,
+    origin: PlayOrigin,
+    options: AudioOptions,
+) -> Result<()> {
+    let mut sound_files = get_sound_files()?;
+
+    let unknown: Vec<&str> = steps
+        .iter()
+        .filter_map(|step| match step {
+            SequenceStep::Sound(name) if !sound_files.contains_key(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    if !unknown.is_empty() {
+        check_msg(
+            channel_id
+                .say(
+                    &ctx.http,
+                    format!(
+                        "I don't know these sounds: **{}**\nType `!list` to see a list of sounds",
+                        unknown.join("**, **")
+                    ),
+                )
+                .await,
+        );
+        return Ok(());
+    }
+
+    // Only keep what the sequence needs to move into the task
+    sound_files.retain(|name, _| steps.contains(&SequenceStep::Sound(name.clone())));
+
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        run_sequence(&ctx, guild_id, steps, gap, origin, options, sound_files).await;
+    });
+
+    Ok(())
+}
+
+async fn run_sequence(
+    ctx: &Context,
+    guild_id: GuildId,
+    steps: Vec<SequenceStep>,
+    gap: Duration,
+    origin: PlayOrigin,
+    options: AudioOptions,
+    sound_files: HashMap<String, SoundFile>,
+) {
+    let mut previous_was_sound = false;
+
+    for step in steps {
+        match step {
+            SequenceStep::Sound(name) => {
+                if previous_was_sound && !gap.is_zero() {
+                    tokio::time::sleep(gap).await;
+                }
+
+                let file = match sound_files.get(&name) {
+                    Some(file) => file,
+                    None => return,
+                };
+                let track = match play_sound(ctx, guild_id, file, origin, &options).await {
+                    Ok(track) => track,
+                    Err(err) => {
+                        error!("Error playing sequence step {}: {}", name, err);
+                        return;
+                    }
+                };
+
+                if !wait_for_track_end(&track).await {
+                    return;
+                }
+                previous_was_sound = true;
+            }
+            SequenceStep::Pause(pause) => {
+                tokio::time::sleep(pause).await;
+                previous_was_sound = false;
+            }
+        }
+    }
+}
+
+/// Waits until the track is over.
+/// Returns true if it played to the end and false if it was stopped early.
+pub async fn wait_for_track_end(track: &TrackHandle) -> bool {
+    let (sender, receiver) = oneshot::channel();
+    let notifier = TrackEndNotifier {
+        sender: StdMutex::new(Some(sender)),
+    };
+
+    // Adding the event fails if the track is already gone
+    if track
+        .add_event(Event::Track(TrackEvent::End), notifier)
+        .is_err()
+    {
+        return false;
+    }
+
+    receiver.await.unwrap_or(false)
+}
+
+struct TrackEndNotifier {
+    sender: StdMutex<Option<oneshot::Sender<bool>>>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let finished = match ctx {
+            EventContext::Track(tracks) => tracks
+                .first()
+                .map_or(false, |(state, _)| state.playing == PlayMode::End),
+            _ => false,
+        };
+
+        if let Ok(mut sender) = self.sender.lock() {
+            if let Some(sender) = sender.take() {
+                let _ = sender.send(finished);
+            }
+        }
+
+        Some(Event::Cancel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pauses() {
+        assert_eq!(parse_pause("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_pause("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_pause("0s"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn sound_names_are_not_pauses() {
+        assert_eq!(parse_pause("tada"), None);
+        assert_eq!(parse_pause("yes"), None);
+        assert_eq!(parse_pause("-1s"), None);
+        assert_eq!(parse_pause("infs"), None);
+    }
+
+    #[test]
+    fn splits_sounds_and_pauses() {
+        assert_eq!(
+            parse_steps(&["drumroll", " 500ms ", "", "tada"]),
+            Ok(vec![
+                SequenceStep::Sound("drumroll".to_string()),
+                SequenceStep::Pause(Duration::from_millis(500)),
+                SequenceStep::Sound("tada".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_long_pauses() {
+        assert!(parse_steps(&["drumroll", "10s", "tada"]).is_ok());
+        assert!(parse_steps(&["drumroll", "11s", "tada"]).is_err());
+        assert!(parse_steps(&["drumroll", "3600000ms"]).is_err());
+        assert!(parse_steps(&["drumroll", "1e300s"]).is_err());
+    }
+}
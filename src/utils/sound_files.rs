@@ -1,36 +1,184 @@
 use anyhow::{Context as AnyhowCtx, Result};
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::Path,
+    path::PathBuf,
+    time::Duration,
+};
+
+use super::{
+    effects::{probe_duration, render_to_file, AudioOptions},
+    tracks::format_timestamp,
+    url_source::{is_url, resolve_public_stream_url},
+};
 
 const ALLOWED_TYPES: [&str; 3] = ["m4a", "wav", "mp3"];
+const SOUND_DIR: &str = "./audio";
+/// Extension of sounds Pascal adds to the library itself
+const NEW_SOUND_EXTENSION: &str = "mp3";
+const MAX_SOUND_NAME_LENGTH: usize = 64;
+/// Longest clip that can be saved to the library, even without a guild limit
+pub const MAX_CLIP_LENGTH: Duration = Duration::from_secs(2 * 60);
 
 pub struct SoundFile {
+    pub name: String,
     pub file_name: String,
     pub file_extension: String,
     pub file_path: PathBuf,
+    /// Name of the sub directory of the sound directory the file lives in, if any
+    pub category: Option<String>,
 }
 
 // TODO: Refactor
-/// Crawls the designated sound file directory for all allowed file extensions
-/// and returns a mapping of sound name (sound file minus extension) to sound file information
+/// Crawls the designated sound file directory and its direct sub directories (categories)
+/// for all allowed file extensions and returns a mapping of sound name
+/// (sound file minus extension) to sound file information
 pub fn get_sound_files() -> Result<HashMap<String, SoundFile>> {
     let mut sound_files: HashMap<String, SoundFile> = HashMap::new();
-    if let Ok(files) = fs::read_dir("./audio") {
+    collect_sound_files(Path::new(SOUND_DIR), None, &mut sound_files)?;
+
+    Ok(sound_files)
+}
+
+/// Checks that a name for a new sound is usable as a file name and as a command argument
+pub fn validate_sound_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().count() > MAX_SOUND_NAME_LENGTH {
+        return Err(format!(
+            "Sound names must be between 1 and {} characters long",
+            MAX_SOUND_NAME_LENGTH
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err("Sound names may only contain letters, digits, `_` and `-`".to_string());
+    }
+
+    Ok(())
+}
+
+/// Returns where a new sound with the given name is stored in the library
+pub fn new_sound_path(name: &str) -> PathBuf {
+    Path::new(SOUND_DIR).join(format!("{}.{}", name, NEW_SOUND_EXTENSION))
+}
+
+/// Creates the file of a new sound, so two sounds of the same name saved at once
+/// can't overwrite each other. Returns None if the file exists already.
+pub fn reserve_sound_path(name: &str) -> Result<Option<PathBuf>> {
+    let path = new_sound_path(name);
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(_) => Ok(Some(path)),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Error creating sound file {:?}", path)),
+    }
+}
+
+/// Renders a library sound or a URL with the given options into a new library sound.
+/// Clips may be `MAX_CLIP_LENGTH` long, or as long as the given limit if that is shorter.
+/// The inner error describes why the clip can't be saved.
+pub async fn save_clip(
+    source: &str,
+    name: &str,
+    options: &AudioOptions,
+    limit: Option<Duration>,
+    downloader: &str,
+    allow_private_hosts: bool,
+) -> Result<std::result::Result<(), String>> {
+    if let Err(err) = validate_sound_name(name) {
+        return Ok(Err(err));
+    }
+
+    let sound_files = get_sound_files()?;
+    if sound_files.contains_key(name) {
+        return Ok(Err(format!("There already is a sound called **{}**", name)));
+    }
+
+    let input = if is_url(source) {
+        match resolve_public_stream_url(downloader, source, allow_private_hosts).await? {
+            Ok(stream_url) => stream_url,
+            Err(reason) => return Ok(Err(reason)),
+        }
+    } else {
+        match sound_files.get(source) {
+            Some(sound_file) => sound_file.file_path.to_string_lossy().into_owned(),
+            None => return Ok(Err(format!("I don't know this sound: **{}**", source))),
+        }
+    };
+
+    let max_length = limit.map_or(MAX_CLIP_LENGTH, |limit| limit.min(MAX_CLIP_LENGTH));
+    // Without a known length, e.g. for live streams, there has to be an end to cut at
+    let length = match probe_duration(&input).await {
+        Some(Some(length)) => Some(options.played_length(length)),
+        _ => options.end.map(|end| options.played_length(end)),
+    };
+    match length {
+        Some(length) if length <= max_length => (),
+        Some(length) => {
+            return Ok(Err(format!(
+                "That clip would be {} long, clips may be {} at most",
+                format_timestamp(length),
+                format_timestamp(max_length)
+            )))
+        }
+        None => {
+            return Ok(Err(
+                "I can't tell how long that is, please set an end to cut it at".to_string(),
+            ))
+        }
+    }
+
+    let path = match reserve_sound_path(name)? {
+        Some(path) => path,
+        None => return Ok(Err(format!("There already is a sound called **{}**", name))),
+    };
+    if let Err(err) = render_to_file(input, options, &path).await {
+        // An empty file would show up as a broken sound
+        let _ = fs::remove_file(&path);
+        return Err(err);
+    }
+
+    Ok(Ok(()))
+}
+
+fn collect_sound_files(
+    dir: &Path,
+    category: Option<&str>,
+    sound_files: &mut HashMap<String, SoundFile>,
+) -> Result<()> {
+    if let Ok(files) = fs::read_dir(dir) {
         for file in files {
             // filter for allowed extensions
             let dir_entry = file.with_context(|| "Error reading directory")?;
             let path = dir_entry.path();
+
+            // Categories are only one level deep
+            if path.is_dir() {
+                if category.is_none() {
+                    if let Some(dir_name) = dir_entry.file_name().to_str() {
+                        collect_sound_files(&path, Some(dir_name), sound_files)?;
+                    }
+                }
+                continue;
+            }
+
             if let Some(extension) = path.extension() {
                 if let Some(ext) = extension.to_str() {
                     if ALLOWED_TYPES.contains(&ext) {
                         // check if file name is the desired one
                         let filename = dir_entry.file_name();
                         if let Some(raw_name) = filename.to_str() {
+                            let name = raw_name.replace(&format!(".{}", ext), "");
                             sound_files.insert(
-                                raw_name.replace(&format!(".{}", ext), ""),
+                                name.clone(),
                                 SoundFile {
+                                    name,
                                     file_name: raw_name.to_owned(),
                                     file_extension: ext.to_owned(),
                                     file_path: path.clone(),
+                                    category: category.map(|c| c.to_owned()),
                                 },
                             );
                         }
@@ -40,5 +188,5 @@ pub fn get_sound_files() -> Result<HashMap<String, SoundFile>> {
         }
     }
 
-    Ok(sound_files)
+    Ok(())
 }
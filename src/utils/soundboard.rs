@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    builder::CreateComponents,
+    client::Context,
+    http::Http,
+    model::{
+        id::{ChannelId, GuildId, MessageId},
+        interactions::message_component::ButtonStyle,
+    },
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{error::handle_error, persistence, sound_files::get_sound_files};
+
+pub const PANEL_STORE_NAME: &str = "soundboard_panels";
+pub const PANEL_BUTTON_PREFIX: &str = "soundboard:";
+
+// Discord allows 5 action rows with 5 buttons each per message
+const BUTTONS_PER_ROW: usize = 5;
+const SOUNDS_PER_PANEL: usize = 25;
+const MAX_LABEL_LENGTH: usize = 80;
+const LIBRARY_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+pub struct PanelStore;
+
+impl TypeMapKey for PanelStore {
+    type Value = Arc<Mutex<SoundboardPanels>>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SoundboardPanels {
+    pub guilds: HashMap<u64, GuildPanels>,
+}
+
+/// The panel messages Pascal maintains in a guild's designated soundboard channel
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GuildPanels {
+    pub channel_id: u64,
+    pub message_ids: Vec<u64>,
+    /// Sound names the panels were last rendered with, used to detect library changes
+    #[serde(default)]
+    pub sounds: Vec<String>,
+}
+
+async fn get_panel_store(ctx: &Context) -> Result<Arc<Mutex<SoundboardPanels>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<PanelStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get soundboard panel store".to_string()))
+}
+
+fn get_sorted_sound_names() -> Result<Vec<String>> {
+    let mut sounds: Vec<String> = get_sound_files()?.into_keys().collect();
+    sounds.sort_unstable();
+
+    Ok(sounds)
+}
+
+/// Designates the given channel as the guild's soundboard channel and posts fresh panels into it.
+/// Panels previously posted elsewhere in the guild are removed.
+pub async fn setup_panels(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Result<()> {
+    let sounds = get_sorted_sound_names()?;
+    let store_lock = get_panel_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    if let Some(old_panels) = store.guilds.remove(guild_id.as_u64()) {
+        delete_panel_messages(&ctx.http, &old_panels).await;
+    }
+
+    let mut panels = GuildPanels {
+        channel_id: *channel_id.as_u64(),
+        ..Default::default()
+    };
+    sync_panels(&ctx.http, &mut panels, &sounds).await?;
+    store.guilds.insert(*guild_id.as_u64(), panels);
+
+    persistence::save(PANEL_STORE_NAME, &*store)
+}
+
+/// Deletes the guild's panels and forgets its soundboard channel.
+/// Returns false if the guild had no panels.
+pub async fn remove_panels(ctx: &Context, guild_id: GuildId) -> Result<bool> {
+    let store_lock = get_panel_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let panels = match store.guilds.remove(guild_id.as_u64()) {
+        Some(panels) => panels,
+        None => return Ok(false),
+    };
+    delete_panel_messages(&ctx.http, &panels).await;
+
+    persistence::save(PANEL_STORE_NAME, &*store)?;
+
+    Ok(true)
+}
+
+/// Reposts or edits the guild's panels to match the sound library.
+/// Returns false if the guild has no panels.
+pub async fn refresh_panels(ctx: &Context, guild_id: GuildId) -> Result<bool> {
+    let sounds = get_sorted_sound_names()?;
+    let store_lock = get_panel_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let panels = match store.guilds.get_mut(guild_id.as_u64()) {
+        Some(panels) => panels,
+        None => return Ok(false),
+    };
+    info!("Refreshing soundboard panels for guild {}", guild_id);
+    sync_panels(&ctx.http, panels, &sounds).await?;
+
+    persistence::save(PANEL_STORE_NAME, &*store)?;
+
+    Ok(true)
+}
+
+/// Brings the panels of every guild up to date with the sound library.
+/// Unless `force` is set, only guilds whose panels show an outdated library are touched.
+pub async fn refresh_all_panels(ctx: &Context, force: bool) -> Result<()> {
+    let sounds = get_sorted_sound_names()?;
+    let store_lock = get_panel_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let mut changed = false;
+    for (guild_id, panels) in store.guilds.iter_mut() {
+        if !force && panels.sounds == sounds {
+            continue;
+        }
+
+        info!("Refreshing soundboard panels for guild {}", guild_id);
+        if let Err(err) = sync_panels(&ctx.http, panels, &sounds).await {
+            error!(
+                "Error refreshing soundboard panels for guild {}: {}",
+                guild_id, err
+            );
+        }
+        changed = true;
+    }
+
+    if changed {
+        persistence::save(PANEL_STORE_NAME, &*store)?;
+    }
+
+    Ok(())
+}
+
+/// Spawns a background task that keeps all panels in sync with the sound library.
+/// The first pass runs right away and repairs panels that went stale while Pascal was offline.
+pub fn start_library_watcher(ctx: Context) {
+    // `ready` fires again on every reconnect, but one watcher is enough
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(LIBRARY_POLL_INTERVAL);
+        let mut force = true;
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = refresh_all_panels(&ctx, force).await {
+                error!("Error refreshing soundboard panels: {}", err);
+            }
+            force = false;
+        }
+    });
+}
+
+/// Edits the existing panel messages in place, recreating those that no longer exist
+/// and deleting those that are no longer needed
+async fn sync_panels(http: &Arc<Http>, panels: &mut GuildPanels, sounds: &[String]) -> Result<()> {
+    let channel_id = ChannelId(panels.channel_id);
+
+    let pages: Vec<&[String]> = if sounds.is_empty() {
+        vec![&[]]
+    } else {
+        sounds.chunks(SOUNDS_PER_PANEL).collect()
+    };
+
+    let mut message_ids = Vec::with_capacity(pages.len());
+    for (index, page) in pages.iter().enumerate() {
+        let content = panel_header(index, pages.len(), page.is_empty());
+
+        if let Some(message_id) = panels.message_ids.get(index) {
+            let edit_result = channel_id
+                .edit_message(http, MessageId(*message_id), |message| {
+                    message
+                        .content(&content)
+                        .components(|components| build_panel_components(components, page))
+                })
+                .await;
+
+            match edit_result {
+                Ok(_) => {
+                    message_ids.push(*message_id);
+                    continue;
+                }
+                Err(err) => warn!(
+                    "Soundboard panel {} in channel {} is stale, recreating it: {}",
+                    message_id, channel_id, err
+                ),
+            }
+        }
+
+        let message = channel_id
+            .send_message(http, |message| {
+                message
+                    .content(&content)
+                    .components(|components| build_panel_components(components, page))
+            })
+            .await?;
+        message_ids.push(*message.id.as_u64());
+    }
+
+    for message_id in panels
+        .message_ids
+        .iter()
+        .filter(|id| !message_ids.contains(id))
+    {
+        if let Err(err) = channel_id
+            .delete_message(http, MessageId(*message_id))
+            .await
+        {
+            warn!("Could not delete soundboard panel {}: {}", message_id, err);
+        }
+    }
+
+    panels.message_ids = message_ids;
+    panels.sounds = sounds.to_vec();
+
+    Ok(())
+}
+
+async fn delete_panel_messages(http: &Arc<Http>, panels: &GuildPanels) {
+    let channel_id = ChannelId(panels.channel_id);
+
+    for message_id in &panels.message_ids {
+        if let Err(err) = channel_id
+            .delete_message(http, MessageId(*message_id))
+            .await
+        {
+            warn!("Could not delete soundboard panel {}: {}", message_id, err);
+        }
+    }
+}
+
+fn panel_header(index: usize, page_count: usize, empty: bool) -> String {
+    if empty {
+        return "**Soundboard**\nNo sounds available yet.".to_string();
+    }
+
+    if page_count > 1 {
+        format!("**Soundboard** ({}/{})", index + 1, page_count)
+    } else {
+        "**Soundboard**".to_string()
+    }
+}
+
+fn build_panel_components<'a>(
+    components: &'a mut CreateComponents,
+    sounds: &[String],
+) -> &'a mut CreateComponents {
+    for row in sounds.chunks(BUTTONS_PER_ROW) {
+        components.create_action_row(|action_row| {
+            for sound in row {
+                action_row.create_button(|button| {
+                    button
+                        .style(ButtonStyle::Secondary)
+                        .label(sound.chars().take(MAX_LABEL_LENGTH).collect::<String>())
+                        .custom_id(format!("{}{}", PANEL_BUTTON_PREFIX, sound))
+                });
+            }
+
+            action_row
+        });
+    }
+
+    components
+}
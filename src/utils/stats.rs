@@ -0,0 +1,243 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::error;
+use rusqlite::{params, Connection, OptionalExtension};
+use serenity::{
+    client::Context,
+    model::id::{GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{
+    error::handle_error,
+    history::{PlayOrigin, PlayedMedia},
+    sound_files::get_sound_files,
+};
+
+const DATABASE_DIR: &str = "./data";
+const DATABASE_FILE: &str = "./data/pascal.db";
+
+pub struct StatsStore;
+
+impl TypeMapKey for StatsStore {
+    type Value = Arc<Mutex<Connection>>;
+}
+
+pub struct SoundStats {
+    pub plays: i64,
+    pub users: i64,
+    pub last_played: i64,
+    pub top_user: Option<(UserId, i64)>,
+}
+
+pub struct UserStats {
+    pub plays: i64,
+    pub top_sounds: Vec<(String, i64)>,
+}
+
+/// Opens the statistics database, creating its schema if necessary
+pub fn open_database() -> Result<Connection> {
+    fs::create_dir_all(DATABASE_DIR).with_context(|| "Error creating data directory")?;
+
+    let conn = Connection::open(DATABASE_FILE)
+        .with_context(|| format!("Error opening database {}", DATABASE_FILE))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS plays (
+            id INTEGER PRIMARY KEY,
+            media TEXT NOT NULL,
+            is_url INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            guild_id INTEGER NOT NULL,
+            interface TEXT NOT NULL,
+            played_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS plays_guild_media ON plays (guild_id, media);
+        CREATE INDEX IF NOT EXISTS plays_guild_user ON plays (guild_id, user_id);",
+    )
+    .with_context(|| "Error creating database schema")?;
+
+    Ok(conn)
+}
+
+async fn get_connection(ctx: &Context) -> Result<Arc<Mutex<Connection>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<StatsStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get stats database".to_string()))
+}
+
+/// Stores a playback in the statistics database. Failures are only logged.
+pub async fn record_play_stat(
+    ctx: &Context,
+    guild_id: GuildId,
+    media: &PlayedMedia,
+    origin: PlayOrigin,
+) {
+    let (media, is_url) = match media {
+        PlayedMedia::Sound(name) => (name.as_str(), false),
+        PlayedMedia::Url(url) => (url.as_str(), true),
+    };
+    let played_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+
+    let result = match get_connection(ctx).await {
+        Ok(conn_lock) => conn_lock
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO plays (media, is_url, user_id, guild_id, interface, played_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    media,
+                    is_url,
+                    *origin.user_id.as_u64() as i64,
+                    *guild_id.as_u64() as i64,
+                    origin.interface.to_string(),
+                    played_at
+                ],
+            )
+            .map(|_| ())
+            .with_context(|| "Error inserting play"),
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        error!("Could not record play statistics: {}", err);
+    }
+}
+
+/// Returns the guild's most played library sounds with their play counts
+pub async fn get_top_sounds(
+    ctx: &Context,
+    guild_id: GuildId,
+    limit: usize,
+) -> Result<Vec<(String, i64)>> {
+    let conn_lock = get_connection(ctx).await?;
+    let conn = conn_lock.lock().await;
+
+    let mut stmt = conn.prepare(
+        "SELECT media, COUNT(*) AS plays FROM plays
+         WHERE guild_id = ?1 AND is_url = 0
+         GROUP BY media ORDER BY plays DESC, media LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![*guild_id.as_u64() as i64, limit as i64], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<(String, i64)>>>()?)
+}
+
+/// Returns how often each library sound has been played in the guild
+pub async fn get_play_counts(ctx: &Context, guild_id: GuildId) -> Result<HashMap<String, i64>> {
+    let conn_lock = get_connection(ctx).await?;
+    let conn = conn_lock.lock().await;
+
+    let mut stmt = conn.prepare(
+        "SELECT media, COUNT(*) FROM plays WHERE guild_id = ?1 AND is_url = 0 GROUP BY media",
+    )?;
+    let rows = stmt.query_map(params![*guild_id.as_u64() as i64], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<HashMap<String, i64>>>()?)
+}
+
+pub async fn get_user_stats(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    limit: usize,
+) -> Result<UserStats> {
+    let conn_lock = get_connection(ctx).await?;
+    let conn = conn_lock.lock().await;
+    let guild = *guild_id.as_u64() as i64;
+    let user = *user_id.as_u64() as i64;
+
+    let plays = conn.query_row(
+        "SELECT COUNT(*) FROM plays WHERE guild_id = ?1 AND user_id = ?2",
+        params![guild, user],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT media, COUNT(*) AS plays FROM plays
+         WHERE guild_id = ?1 AND user_id = ?2 AND is_url = 0
+         GROUP BY media ORDER BY plays DESC, media LIMIT ?3",
+    )?;
+    let top_sounds = stmt
+        .query_map(params![guild, user, limit as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(String, i64)>>>()?;
+
+    Ok(UserStats { plays, top_sounds })
+}
+
+/// Returns None if the sound has never been played in the guild
+pub async fn get_sound_stats(
+    ctx: &Context,
+    guild_id: GuildId,
+    sound: &str,
+) -> Result<Option<SoundStats>> {
+    let conn_lock = get_connection(ctx).await?;
+    let conn = conn_lock.lock().await;
+    let guild = *guild_id.as_u64() as i64;
+
+    let (plays, users, last_played): (i64, i64, Option<i64>) = conn.query_row(
+        "SELECT COUNT(*), COUNT(DISTINCT user_id), MAX(played_at) FROM plays
+         WHERE guild_id = ?1 AND media = ?2 AND is_url = 0",
+        params![guild, sound],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    let last_played = match last_played {
+        Some(last_played) if plays > 0 => last_played,
+        _ => return Ok(None),
+    };
+
+    let top_user = conn
+        .query_row(
+            "SELECT user_id, COUNT(*) AS plays FROM plays
+             WHERE guild_id = ?1 AND media = ?2 AND is_url = 0
+             GROUP BY user_id ORDER BY plays DESC LIMIT 1",
+            params![guild, sound],
+            |row| Ok((UserId(row.get::<_, i64>(0)? as u64), row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(Some(SoundStats {
+        plays,
+        users,
+        last_played,
+        top_user,
+    }))
+}
+
+/// Returns the library sounds that have never been played in any guild, sorted by name
+pub async fn get_unplayed_sounds(ctx: &Context) -> Result<Vec<String>> {
+    let conn_lock = get_connection(ctx).await?;
+    let conn = conn_lock.lock().await;
+
+    let mut stmt = conn.prepare("SELECT DISTINCT media FROM plays WHERE is_url = 0")?;
+    let played = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+
+    let mut unplayed: Vec<String> = get_sound_files()?
+        .into_keys()
+        .filter(|name| !played.contains(name))
+        .collect();
+    unplayed.sort_unstable();
+
+    Ok(unplayed)
+}
@@ -0,0 +1,323 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use log::error;
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId, UserId},
+    prelude::{Mutex, TypeMapKey},
+};
+use tokio::task::JoinHandle;
+
+use super::{
+    discord::{get_channel_of_member, join_channel, play_from_file_with_options},
+    effects::AudioOptions,
+    error::{check_msg, handle_error},
+    history::{PlayInterface, PlayOrigin},
+};
+
+pub const MAX_TIMERS_PER_USER: usize = 10;
+pub const MAX_TIMER_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest reminder in characters, well below what fits in a Discord message
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+pub struct TimersStore;
+
+impl TypeMapKey for TimersStore {
+    type Value = Arc<Mutex<Timers>>;
+}
+
+/// Running timers. They are lost on restart, like the tasks waiting for them.
+#[derive(Default)]
+pub struct Timers {
+    next_id: u64,
+    timers: HashMap<u64, Timer>,
+}
+
+struct Timer {
+    guild_id: GuildId,
+    user_id: UserId,
+    /// Text channel the timer was set in, where it's announced
+    channel_id: ChannelId,
+    sound: String,
+    message: Option<String>,
+    ends_at: Instant,
+    task: JoinHandle<()>,
+}
+
+/// A running timer as shown to its owner
+pub struct TimerInfo {
+    pub id: u64,
+    pub sound: String,
+    pub message: Option<String>,
+    pub remaining: Duration,
+}
+
+/// Parses durations like `10m`, `1h30m` or `45s`. Plain numbers are minutes.
+pub fn parse_timer_duration(text: &str) -> std::result::Result<Duration, String> {
+    let invalid = || format!("**{}** is not a duration like 10m, 1h30m or 45s.", text);
+    let text = text.trim().to_lowercase();
+
+    if text.chars().all(|c| c.is_ascii_digit()) && !text.is_empty() {
+        let minutes = text.parse::<u64>().ok();
+        return check_duration(minutes.and_then(|minutes| minutes.checked_mul(60)));
+    }
+
+    // Seconds so far, None once they don't fit into a u64 anymore
+    let mut total = Some(0u64);
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        if number.is_empty() {
+            return Err(invalid());
+        }
+        let unit = match c {
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let secs = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|value| value.checked_mul(unit));
+        total = total
+            .zip(secs)
+            .and_then(|(total, secs)| total.checked_add(secs));
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    check_duration(total)
+}
+
+/// Checks the parsed seconds against the allowed range, None meaning too many to count
+fn check_duration(secs: Option<u64>) -> std::result::Result<Duration, String> {
+    match secs {
+        Some(0) => Err("The timer has to run for at least a second.".to_string()),
+        Some(secs) if secs <= MAX_TIMER_DURATION.as_secs() => Ok(Duration::from_secs(secs)),
+        _ => Err("Timers can run for a day at most.".to_string()),
+    }
+}
+
+async fn get_timers_store(ctx: &Context) -> Result<Arc<Mutex<Timers>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<TimersStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get timers store".to_string()))
+}
+
+/// Starts a timer that plays the sound in the user's voice channel once it runs out.
+/// Returns its ID, or the reason if the user has too many timers.
+pub async fn start_timer(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: ChannelId,
+    duration: Duration,
+    sound: &str,
+    message: Option<&str>,
+) -> Result<std::result::Result<u64, String>> {
+    if let Some(message) = message {
+        if message.chars().count() > MAX_MESSAGE_LENGTH {
+            return Ok(Err(format!(
+                "Reminders can be {} characters long at most.",
+                MAX_MESSAGE_LENGTH
+            )));
+        }
+    }
+
+    let store_lock = get_timers_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let running = store
+        .timers
+        .values()
+        .filter(|timer| timer.guild_id == guild_id && timer.user_id == user_id)
+        .count();
+    if running >= MAX_TIMERS_PER_USER {
+        return Ok(Err(format!(
+            "You can't have more than {} timers, please cancel one first.",
+            MAX_TIMERS_PER_USER
+        )));
+    }
+
+    store.next_id += 1;
+    let id = store.next_id;
+
+    let timer_ctx = ctx.clone();
+    let task = tokio::spawn(async move {
+        tokio::time::sleep(duration).await;
+        if let Err(err) = finish_timer(&timer_ctx, id).await {
+            error!("Error finishing timer {}: {}", id, err);
+        }
+    });
+
+    store.timers.insert(
+        id,
+        Timer {
+            guild_id,
+            user_id,
+            channel_id,
+            sound: sound.to_owned(),
+            message: message.map(str::to_owned),
+            ends_at: Instant::now() + duration,
+            task,
+        },
+    );
+
+    Ok(Ok(id))
+}
+
+/// Returns the user's running timers in the guild, soonest first
+pub async fn get_timers(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<Vec<TimerInfo>> {
+    let store_lock = get_timers_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    let now = Instant::now();
+    let mut timers: Vec<TimerInfo> = store
+        .timers
+        .iter()
+        .filter(|(_, timer)| timer.guild_id == guild_id && timer.user_id == user_id)
+        .map(|(id, timer)| TimerInfo {
+            id: *id,
+            sound: timer.sound.clone(),
+            message: timer.message.clone(),
+            remaining: timer.ends_at.saturating_duration_since(now),
+        })
+        .collect();
+    timers.sort_unstable_by_key(|timer| timer.remaining);
+
+    Ok(timers)
+}
+
+/// Cancels one of the user's timers. Returns false if they have no timer with that ID.
+pub async fn cancel_timer(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    id: u64,
+) -> Result<bool> {
+    let store_lock = get_timers_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let owned = store.timers.get(&id).map_or(false, |timer| {
+        timer.guild_id == guild_id && timer.user_id == user_id
+    });
+    if !owned {
+        return Ok(false);
+    }
+
+    if let Some(timer) = store.timers.remove(&id) {
+        timer.task.abort();
+    }
+
+    Ok(true)
+}
+
+async fn finish_timer(ctx: &Context, id: u64) -> Result<()> {
+    let timer = {
+        let store_lock = get_timers_store(ctx).await?;
+        let mut store = store_lock.lock().await;
+        match store.timers.remove(&id) {
+            Some(timer) => timer,
+            // Cancelled just as it ran out
+            None => return Ok(()),
+        }
+    };
+
+    let announcement = match &timer.message {
+        Some(message) => format!("⏰ <@{}> {}", timer.user_id, message),
+        None => format!("⏰ <@{}> Time's up!", timer.user_id),
+    };
+    // The reminder is the user's own text, which must not ping anyone but them
+    check_msg(
+        timer
+            .channel_id
+            .send_message(&ctx.http, |message| {
+                message
+                    .content(announcement)
+                    .allowed_mentions(|mentions| mentions.empty_parse().users(vec![timer.user_id]))
+            })
+            .await,
+    );
+
+    // The sound follows the user to wherever they are now
+    let voice_channel_id =
+        match get_channel_of_member(ctx.clone(), timer.guild_id, timer.user_id).await {
+            Some(channel_id) => channel_id,
+            None => return Ok(()),
+        };
+    join_channel(ctx, timer.guild_id, voice_channel_id).await?;
+
+    let origin = PlayOrigin::new(timer.user_id, PlayInterface::Timer);
+    play_from_file_with_options(
+        ctx,
+        timer.channel_id,
+        timer.guild_id,
+        &timer.sound,
+        origin,
+        &AudioOptions::default(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_timer_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_timer_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_timer_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_timer_duration(" 2H "), Ok(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn plain_numbers_are_minutes() {
+        assert_eq!(parse_timer_duration("5"), Ok(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_timer_duration("").is_err());
+        assert!(parse_timer_duration("soon").is_err());
+        assert!(parse_timer_duration("10").is_ok());
+        assert!(parse_timer_duration("10x").is_err());
+        assert!(parse_timer_duration("1h30").is_err());
+        assert!(parse_timer_duration("m").is_err());
+        assert!(parse_timer_duration("0s").is_err());
+    }
+
+    #[test]
+    fn rejects_durations_over_a_day() {
+        assert_eq!(parse_timer_duration("24h"), Ok(MAX_TIMER_DURATION));
+        assert!(parse_timer_duration("24h1s").is_err());
+        assert!(parse_timer_duration("1441").is_err());
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        assert!(parse_timer_duration("400000000000000000").is_err());
+        assert!(parse_timer_duration("400000000000000000h").is_err());
+        assert!(parse_timer_duration("18446744073709551615s1s").is_err());
+        assert!(parse_timer_duration("99999999999999999999999s").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use log::{info, warn};
+use serenity::{
+    async_trait,
+    client::Context,
+    model::id::GuildId,
+    prelude::{Mutex, TypeMapKey},
+};
+use songbird::{
+    tracks::{PlayMode, TrackHandle},
+    Event, EventContext, EventHandler as VoiceEventHandler,
+};
+
+use super::{config::FadeConfig, error::handle_error};
+
+const FADE_STEPS: u32 = 20;
+
+/// Handles of the tracks each guild is currently playing, oldest first
+pub struct TrackStore;
+
+impl TypeMapKey for TrackStore {
+    type Value = Arc<Mutex<HashMap<GuildId, Vec<TrackHandle>>>>;
+}
+
+pub struct FadeStore;
+
+impl TypeMapKey for FadeStore {
+    type Value = FadeConfig;
+}
+
+async fn get_track_store(ctx: &Context) -> Result<Arc<Mutex<HashMap<GuildId, Vec<TrackHandle>>>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<TrackStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get track store".to_string()))
+}
+
+async fn is_active(track: &TrackHandle) -> bool {
+    match track.get_info().await {
+        Ok(state) => matches!(state.playing, PlayMode::Play | PlayMode::Pause),
+        Err(_) => false,
+    }
+}
+
+/// Remembers the handle of a track that has just started
+pub async fn register_track(ctx: &Context, guild_id: GuildId, track: TrackHandle) -> Result<()> {
+    let store_lock = get_track_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+    let tracks = store.entry(guild_id).or_default();
+
+    // Forget tracks that are over while we're at it
+    let mut active = Vec::with_capacity(tracks.len() + 1);
+    for existing in tracks.drain(..) {
+        if is_active(&existing).await {
+            active.push(existing);
+        }
+    }
+    active.push(track);
+    *tracks = active;
+
+    Ok(())
+}
+
+/// Returns the guild's tracks that are still playing or paused, oldest first
+pub async fn get_active_tracks(ctx: &Context, guild_id: GuildId) -> Result<Vec<TrackHandle>> {
+    let store_lock = get_track_store(ctx).await?;
+    let tracks = store_lock
+        .lock()
+        .await
+        .get(&guild_id)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut active = Vec::with_capacity(tracks.len());
+    for track in tracks {
+        if is_active(&track).await {
+            active.push(track);
+        }
+    }
+
+    Ok(active)
+}
+
+/// Returns the most recently started track of the guild that is still playing
+pub async fn get_current_track(ctx: &Context, guild_id: GuildId) -> Result<Option<TrackHandle>> {
+    Ok(get_active_tracks(ctx, guild_id).await?.pop())
+}
+
+async fn get_fade_config(ctx: &Context) -> Result<FadeConfig> {
+    ctx.data
+        .read()
+        .await
+        .get::<FadeStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get fade config".to_string()))
+}
+
+/// How long tracks take to fade out when they're stopped, skipped or cut off
+pub async fn get_fade_out(ctx: &Context) -> Duration {
+    get_fade_config(ctx)
+        .await
+        .map(|config| config.fade_out())
+        .unwrap_or_default()
+}
+
+/// How long the sound takes to fade in, if it's supposed to
+pub async fn get_fade_in(ctx: &Context, sound_name: &str) -> Option<Duration> {
+    get_fade_config(ctx)
+        .await
+        .ok()
+        .and_then(|config| config.fade_in(sound_name))
+}
+
+/// Moves the track's volume to the target in small steps.
+/// Returns false if the track ended in the meantime.
+async fn ramp_volume(track: &TrackHandle, from: f32, to: f32, duration: Duration) -> bool {
+    let step_duration = duration / FADE_STEPS;
+    for step in 1..=FADE_STEPS {
+        tokio::time::sleep(step_duration).await;
+
+        let volume = from + (to - from) * step as f32 / FADE_STEPS as f32;
+        if track.set_volume(volume).is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Raises the volume of a track that started muted to full level in the background
+pub fn start_fade_in(track: TrackHandle, fade: Duration) {
+    tokio::spawn(async move { ramp_volume(&track, 0.0, 1.0, fade).await });
+}
+
+/// Lowers the track's volume to silence over the given duration and stops it
+pub async fn fade_out_and_stop(track: &TrackHandle, fade: Duration) {
+    if !fade.is_zero() {
+        let volume = match track.get_info().await {
+            Ok(state) => state.volume,
+            // The track is already over
+            Err(_) => return,
+        };
+        if !ramp_volume(track, volume, 0.0, fade).await {
+            return;
+        }
+    }
+
+    if let Err(err) = track.stop() {
+        warn!("Could not stop track: {}", err);
+    }
+}
+
+/// Fades the track out once it has played for the given time
+pub fn enforce_duration_limit(track: &TrackHandle, limit: Duration, fade: Duration) -> Result<()> {
+    // Start fading early so the track is silent right when the limit is reached
+    let fade = fade.min(limit);
+    let limiter = DurationLimiter { limit, fade };
+
+    track
+        .add_event(Event::Delayed(limit - fade), limiter)
+        .map_err(|err| handle_error(format!("Could not watch track duration: {}", err)))
+}
+
+struct DurationLimiter {
+    limit: Duration,
+    fade: Duration,
+}
+
+#[async_trait]
+impl VoiceEventHandler for DurationLimiter {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (_, track) in tracks.iter() {
+                info!(
+                    "Track reached the duration limit of {}, stopping it",
+                    format_timestamp(self.limit)
+                );
+
+                // Fading takes a while, which mustn't hold up other track events
+                let track = (*track).clone();
+                let fade = self.fade;
+                tokio::spawn(async move { fade_out_and_stop(&track, fade).await });
+            }
+        }
+
+        None
+    }
+}
+
+/// Parses positions like `83`, `1:23` or `1:02:03.5`
+pub fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for (index, part) in timestamp.trim().split(':').enumerate() {
+        // Hours at most
+        if index > 2 {
+            return None;
+        }
+
+        let value = part.parse::<f64>().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    // Values too large for a duration are nonsense, not a reason to crash
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Formats a duration as `m:ss`, or `h:mm:ss` for durations of an hour or more
+pub fn format_timestamp(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_seconds_minutes_and_hours() {
+        assert_eq!(parse_timestamp("83"), Some(Duration::from_secs(83)));
+        assert_eq!(parse_timestamp("4.5"), Some(Duration::from_millis(4500)));
+        assert_eq!(parse_timestamp("1:23"), Some(Duration::from_secs(83)));
+        assert_eq!(
+            parse_timestamp(" 1:02:03.5 "),
+            Some(Duration::from_millis(3_723_500))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("abc"), None);
+        assert_eq!(parse_timestamp("-5"), None);
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+        assert_eq!(parse_timestamp("inf"), None);
+        assert_eq!(parse_timestamp("NaN"), None);
+    }
+
+    #[test]
+    fn rejects_timestamps_too_large_for_a_duration() {
+        assert_eq!(parse_timestamp("1e300"), None);
+        assert_eq!(parse_timestamp("1e300:00"), None);
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(format_timestamp(Duration::from_secs(83)), "1:23");
+        assert_eq!(format_timestamp(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(format_timestamp(Duration::from_secs(0)), "0:00");
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serenity::{
+    client::Context,
+    model::id::{ChannelId, GuildId},
+    prelude::{Mutex, TypeMapKey},
+};
+
+use super::{error::handle_error, persistence};
+
+pub const TRIGGERS_STORE_NAME: &str = "triggers";
+
+/// Every message is matched against all of the guild's triggers, so keep that cheap
+pub const MAX_TRIGGERS_PER_GUILD: usize = 50;
+// Keeps user supplied patterns from compiling into huge automatons
+const MAX_REGEX_SIZE: usize = 1 << 16;
+
+pub struct TriggersStore;
+
+impl TypeMapKey for TriggersStore {
+    type Value = Arc<Mutex<Triggers>>;
+}
+
+/// Text triggers that play a sound when a chat message matches, per guild
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Triggers {
+    pub guilds: HashMap<u64, HashMap<String, Trigger>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerKind {
+    /// Matches the pattern as a whole word, ignoring case
+    Word,
+    /// Matches the pattern as a case insensitive regex
+    Regex,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trigger {
+    pub pattern: String,
+    pub kind: TriggerKind,
+    pub sound: String,
+    pub cooldown_secs: u64,
+    /// Channels the trigger listens in
+    pub channels: Vec<u64>,
+    pub created_by: u64,
+    #[serde(skip)]
+    regex: Option<Regex>,
+    #[serde(skip)]
+    last_fired: Option<Instant>,
+}
+
+impl Trigger {
+    /// Creates a trigger, returning the reason if the pattern is invalid
+    pub fn new(
+        pattern: &str,
+        kind: TriggerKind,
+        sound: &str,
+        cooldown: Duration,
+        channels: Vec<ChannelId>,
+        created_by: u64,
+    ) -> std::result::Result<Self, String> {
+        let regex = build_regex(pattern, kind)?;
+
+        Ok(Trigger {
+            pattern: pattern.to_owned(),
+            kind,
+            sound: sound.to_owned(),
+            cooldown_secs: cooldown.as_secs(),
+            channels: channels.iter().map(|channel| *channel.as_u64()).collect(),
+            created_by,
+            regex: Some(regex),
+            last_fired: None,
+        })
+    }
+
+    fn is_cooling_down(&self, now: Instant) -> bool {
+        self.last_fired.map_or(false, |last_fired| {
+            now.duration_since(last_fired) < Duration::from_secs(self.cooldown_secs)
+        })
+    }
+
+    /// Compiles the pattern on first use, since compiled patterns aren't persisted
+    fn matches(&mut self, content: &str) -> bool {
+        if self.regex.is_none() {
+            self.regex = build_regex(&self.pattern, self.kind).ok();
+        }
+
+        self.regex
+            .as_ref()
+            .map_or(false, |regex| regex.is_match(content))
+    }
+}
+
+fn build_regex(pattern: &str, kind: TriggerKind) -> std::result::Result<Regex, String> {
+    if pattern.trim().is_empty() {
+        return Err("The pattern must not be empty.".to_string());
+    }
+
+    let source = match kind {
+        TriggerKind::Word => format!(r"\b{}\b", regex::escape(pattern.trim())),
+        TriggerKind::Regex => pattern.to_owned(),
+    };
+
+    RegexBuilder::new(&source)
+        .case_insensitive(true)
+        .size_limit(MAX_REGEX_SIZE)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+async fn get_triggers_store(ctx: &Context) -> Result<Arc<Mutex<Triggers>>> {
+    ctx.data
+        .read()
+        .await
+        .get::<TriggersStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get triggers store".to_string()))
+}
+
+/// Returns the guild's triggers sorted by name
+pub async fn get_triggers(ctx: &Context, guild_id: GuildId) -> Result<Vec<(String, Trigger)>> {
+    let store_lock = get_triggers_store(ctx).await?;
+    let store = store_lock.lock().await;
+
+    let mut triggers: Vec<(String, Trigger)> = store
+        .guilds
+        .get(guild_id.as_u64())
+        .map(|triggers| {
+            triggers
+                .iter()
+                .map(|(name, trigger)| (name.clone(), trigger.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    triggers.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(triggers)
+}
+
+/// Saves a trigger, replacing an existing trigger of the same name.
+/// Returns the reason if the guild has too many triggers.
+pub async fn save_trigger(
+    ctx: &Context,
+    guild_id: GuildId,
+    name: &str,
+    trigger: Trigger,
+) -> Result<std::result::Result<(), String>> {
+    let store_lock = get_triggers_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let triggers = store.guilds.entry(*guild_id.as_u64()).or_default();
+    if !triggers.contains_key(name) && triggers.len() >= MAX_TRIGGERS_PER_GUILD {
+        return Ok(Err(format!(
+            "There can't be more than {} triggers, please remove one first.",
+            MAX_TRIGGERS_PER_GUILD
+        )));
+    }
+    triggers.insert(name.to_owned(), trigger);
+
+    persistence::save(TRIGGERS_STORE_NAME, &*store)?;
+
+    Ok(Ok(()))
+}
+
+/// Returns false if there was no trigger of that name
+pub async fn delete_trigger(ctx: &Context, guild_id: GuildId, name: &str) -> Result<bool> {
+    let store_lock = get_triggers_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let removed = store
+        .guilds
+        .get_mut(guild_id.as_u64())
+        .and_then(|triggers| triggers.remove(name))
+        .is_some();
+    if removed {
+        persistence::save(TRIGGERS_STORE_NAME, &*store)?;
+    }
+
+    Ok(removed)
+}
+
+/// Finds a trigger listening in the channel that matches the message and isn't cooling down.
+/// Starts its cooldown and returns the sound to play.
+pub async fn fire_trigger(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    content: &str,
+) -> Result<Option<String>> {
+    let store_lock = get_triggers_store(ctx).await?;
+    let mut store = store_lock.lock().await;
+
+    let triggers = match store.guilds.get_mut(guild_id.as_u64()) {
+        Some(triggers) => triggers,
+        None => return Ok(None),
+    };
+
+    let now = Instant::now();
+    for trigger in triggers.values_mut() {
+        if !trigger.channels.contains(channel_id.as_u64()) || trigger.is_cooling_down(now) {
+            continue;
+        }
+
+        if trigger.matches(content) {
+            trigger.last_fired = Some(now);
+            return Ok(Some(trigger.sound.clone()));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::warn;
+use serenity::{client::Context, model::id::GuildId, prelude::TypeMapKey};
+use songbird::tracks::TrackHandle;
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+use super::{
+    config::{TtsConfig, TtsEngine},
+    discord::play_speech,
+    error::handle_error,
+    history::PlayOrigin,
+    sound_files::SoundFile,
+};
+
+const TTS_DIR: &str = "./data/tts";
+const SYNTHESIS_TIMEOUT: Duration = Duration::from_secs(30);
+/// Synthesized speech is kept so repeated greetings don't have to be synthesized again
+const MAX_TTS_FILES: usize = 100;
+/// Sound name speech is played and recorded under
+pub const TTS_SOUND_NAME: &str = "tts";
+// espeak-ng's default rate in words per minute
+const ESPEAK_DEFAULT_WPM: f32 = 175.0;
+
+pub struct TtsStore;
+
+impl TypeMapKey for TtsStore {
+    type Value = TtsConfig;
+}
+
+pub async fn get_tts_config(ctx: &Context) -> Result<TtsConfig> {
+    ctx.data
+        .read()
+        .await
+        .get::<TtsStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get TTS config".to_string()))
+}
+
+/// Collapses whitespace and checks the text against the configured maximum length
+fn prepare_text(config: &TtsConfig, text: &str) -> std::result::Result<String, String> {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if text.is_empty() {
+        return Err("Please give me something to say.".to_string());
+    }
+
+    let length = text.chars().count();
+    if length > config.max_length {
+        return Err(format!(
+            "That's {} characters, I only say up to {}.",
+            length, config.max_length
+        ));
+    }
+
+    Ok(text)
+}
+
+fn speech_path(config: &TtsConfig, text: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    config.binary().hash(&mut hasher);
+    config.voice.hash(&mut hasher);
+    config.speed.to_bits().hash(&mut hasher);
+    text.hash(&mut hasher);
+
+    PathBuf::from(TTS_DIR).join(format!("{:016x}.wav", hasher.finish()))
+}
+
+fn engine_command(config: &TtsConfig, destination: &Path) -> Command {
+    let speed = if config.speed > 0.0 {
+        config.speed
+    } else {
+        1.0
+    };
+
+    let mut command = Command::new(config.binary());
+    match config.engine {
+        TtsEngine::EspeakNg => {
+            let words_per_minute = (ESPEAK_DEFAULT_WPM * speed).round() as u32;
+            command
+                .args(&["--stdin", "-v", &config.voice, "-s"])
+                .arg(words_per_minute.to_string())
+                .arg("-w")
+                .arg(destination);
+        }
+        TtsEngine::Piper => {
+            command
+                .args(&["--model", &config.voice, "--length_scale"])
+                .arg((1.0 / speed).to_string())
+                .arg("--output_file")
+                .arg(destination);
+        }
+    }
+
+    command
+}
+
+/// Synthesizes the text into a WAV file with the configured engine.
+/// Returns the reason if the text can't be spoken.
+pub async fn synthesize(
+    config: &TtsConfig,
+    text: &str,
+) -> Result<std::result::Result<SoundFile, String>> {
+    let text = match prepare_text(config, text) {
+        Ok(text) => text,
+        Err(reason) => return Ok(Err(reason)),
+    };
+
+    let path = speech_path(config, &text);
+    if !path.exists() {
+        fs::create_dir_all(TTS_DIR).with_context(|| "Error creating TTS directory")?;
+
+        if let Err(reason) = run_engine(config, &text, &path).await? {
+            // Don't keep a half written file around for the next time
+            let _ = fs::remove_file(&path);
+            return Ok(Err(reason));
+        }
+        prune_speech_files();
+    }
+
+    Ok(Ok(SoundFile {
+        name: TTS_SOUND_NAME.to_string(),
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        file_extension: "wav".to_string(),
+        file_path: path,
+        category: None,
+    }))
+}
+
+async fn run_engine(
+    config: &TtsConfig,
+    text: &str,
+    destination: &Path,
+) -> Result<std::result::Result<(), String>> {
+    // The text goes in through stdin, so nothing in it can be mistaken for an argument
+    let mut child = engine_command(config, destination)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Error running {}", config.binary()))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .await
+            .with_context(|| format!("Error writing text to {}", config.binary()))?;
+    }
+
+    let output = match timeout(SYNTHESIS_TIMEOUT, child.wait_with_output()).await {
+        Ok(output) => output.with_context(|| format!("Error running {}", config.binary()))?,
+        Err(_) => return Ok(Err("Speaking that took too long.".to_string())),
+    };
+
+    if !output.status.success() || !destination.exists() {
+        warn!(
+            "{} could not synthesize speech: {}",
+            config.binary(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Ok(Err("I couldn't say that.".to_string()));
+    }
+
+    Ok(Ok(()))
+}
+
+/// Deletes the oldest speech files once there are more than `MAX_TTS_FILES`
+fn prune_speech_files() {
+    let entries = match fs::read_dir(TTS_DIR) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Could not read TTS directory: {}", err);
+            return;
+        }
+    };
+
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if files.len() <= MAX_TTS_FILES {
+        return;
+    }
+
+    files.sort_unstable_by_key(|(modified, _)| *modified);
+    for (_, path) in files.iter().take(files.len() - MAX_TTS_FILES) {
+        if let Err(err) = fs::remove_file(path) {
+            warn!("Could not delete old speech file {:?}: {}", path, err);
+        }
+    }
+}
+
+/// Synthesizes the text and plays it, without it showing up in the history or stats.
+/// Pascal has to be in a voice channel of the guild already.
+pub async fn speak(
+    ctx: &Context,
+    guild_id: GuildId,
+    text: &str,
+    origin: PlayOrigin,
+) -> Result<std::result::Result<TrackHandle, String>> {
+    let config = get_tts_config(ctx).await?;
+    let sound_file = match synthesize(&config, text).await? {
+        Ok(sound_file) => sound_file,
+        Err(reason) => return Ok(Err(reason)),
+    };
+
+    let track = play_speech(ctx, guild_id, &sound_file, origin).await?;
+
+    Ok(Ok(track))
+}
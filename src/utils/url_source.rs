@@ -0,0 +1,380 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{Context as AnyhowCtx, Result};
+use log::{debug, warn};
+use serenity::{client::Context, model::id::GuildId, prelude::TypeMapKey};
+use songbird::input::Input;
+use tokio::{net::lookup_host, process::Command, time::timeout};
+
+use super::{
+    config::UrlConfig,
+    effects::{filtered_source, probe_duration, resolve_stream_url, AudioOptions},
+    error::handle_error,
+    guild_settings::get_duration_limits,
+    import::get_downloader,
+    media_cache::{cache_in_background, get_cached},
+    tracks::{format_timestamp, parse_timestamp},
+};
+
+/// Sites whose pages need youtube-dl to get to the audio, no need to probe them
+const YTDL_DOMAINS: [&str; 7] = [
+    "youtube.com",
+    "youtu.be",
+    "soundcloud.com",
+    "bandcamp.com",
+    "vimeo.com",
+    "twitch.tv",
+    "twitter.com",
+];
+const AUDIO_EXTENSIONS: [&str; 8] = ["mp3", "ogg", "opus", "wav", "flac", "m4a", "aac", "webm"];
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub struct UrlStore;
+
+impl TypeMapKey for UrlStore {
+    type Value = UrlConfig;
+}
+
+/// How the audio behind a URL is read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UrlKind {
+    /// A page of a video site, resolved through youtube-dl
+    Ytdl,
+    /// An audio file ffmpeg can read directly
+    AudioFile,
+    /// An endless stream like an Icecast radio station
+    Stream,
+}
+
+pub fn is_url(item: &str) -> bool {
+    item.starts_with("https://") || item.starts_with("http://")
+}
+
+/// Returns the lower case host of a URL without user info and port
+pub fn get_host(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(|c| c == '/' || c == '?' || c == '#').next()?;
+    let host = authority.rsplit('@').next()?;
+    // IPv6 addresses come in brackets, as they are full of colons themselves
+    let host = match host.strip_prefix('[') {
+        Some(address) => address.split(']').next()?,
+        None => host.split(':').next()?,
+    }
+    .trim_end_matches('.');
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_lowercase())
+    }
+}
+
+/// True if the host is the domain itself or one of its subdomains
+fn matches_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// True for addresses on the internet, false for Pascal's own machine and local networks
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || first == 0
+                // Carrier grade NAT
+                || (first == 100 && second & 0b1100_0000 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local and link local addresses
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Checks that the host is on the internet, so nobody can have Pascal fetch from its own
+/// machine or network, like a cloud metadata endpoint. Names are resolved for the check,
+/// so they can't point there either. Redirects ffmpeg follows later, or a name resolving
+/// differently by then, aren't covered by this.
+async fn is_public_host(host: &str) -> bool {
+    if host == "localhost" || host.ends_with(".localhost") {
+        return false;
+    }
+
+    match lookup_host((host, 0)).await {
+        Ok(addresses) => {
+            let addresses: Vec<SocketAddr> = addresses.collect();
+            !addresses.is_empty() && addresses.iter().all(|address| is_public_ip(address.ip()))
+        }
+        Err(err) => {
+            debug!("Could not resolve {}: {}", host, err);
+            false
+        }
+    }
+}
+
+fn not_public(host: &str) -> String {
+    format!(
+        "I only play from the public internet, not from **{}**",
+        host
+    )
+}
+
+/// Asks the downloader for the direct stream behind a page URL. The downloader follows
+/// redirects on its own, so unless private hosts are allowed, the stream's host is checked too.
+/// The inner error describes why the stream can't be played.
+pub async fn resolve_public_stream_url(
+    downloader: &str,
+    url: &str,
+    allow_private_hosts: bool,
+) -> Result<std::result::Result<String, String>> {
+    let stream_url = resolve_stream_url(downloader, url).await?;
+    if !allow_private_hosts {
+        let host = get_host(&stream_url).unwrap_or_default();
+        if !is_public_host(&host).await {
+            return Ok(Err(not_public(&host)));
+        }
+    }
+
+    Ok(Ok(stream_url))
+}
+
+pub async fn get_url_config(ctx: &Context) -> Result<UrlConfig> {
+    ctx.data
+        .read()
+        .await
+        .get::<UrlStore>()
+        .cloned()
+        .ok_or_else(|| handle_error("Unable to get URL config".to_string()))
+}
+
+/// Checks the URL against the configured domain lists and the duration limit of the guild,
+/// if given, and figures out how its audio has to be read.
+/// The inner error describes why the URL can't be played.
+pub async fn classify_url(
+    ctx: &Context,
+    url: &str,
+    guild_id: Option<GuildId>,
+) -> Result<std::result::Result<UrlKind, String>> {
+    let host = match get_host(url) {
+        Some(host) => host,
+        None => return Ok(Err(format!("That doesn't look like a URL: <{}>", url))),
+    };
+
+    let config = get_url_config(ctx).await?;
+    if config
+        .denied_domains
+        .iter()
+        .any(|domain| matches_domain(&host, domain))
+    {
+        return Ok(Err(format!(
+            "I'm not allowed to play anything from **{}**",
+            host
+        )));
+    }
+    if !config.allowed_domains.is_empty()
+        && !config
+            .allowed_domains
+            .iter()
+            .any(|domain| matches_domain(&host, domain))
+    {
+        return Ok(Err(format!(
+            "I'm not allowed to play anything from **{}**",
+            host
+        )));
+    }
+    if !config.allow_private_hosts && !is_public_host(&host).await {
+        return Ok(Err(not_public(&host)));
+    }
+
+    let downloader = get_downloader(ctx).await?;
+    let (kind, duration) = if YTDL_DOMAINS
+        .iter()
+        .any(|domain| matches_domain(&host, domain))
+    {
+        (UrlKind::Ytdl, probe_ytdl_duration(&downloader, url).await)
+    } else {
+        match probe_duration(url).await {
+            // ffprobe knows no duration for live streams
+            Some(None) => (UrlKind::Stream, None),
+            Some(duration) => (UrlKind::AudioFile, duration),
+            None if has_audio_extension(url) => (UrlKind::AudioFile, None),
+            None => (UrlKind::Ytdl, probe_ytdl_duration(&downloader, url).await),
+        }
+    };
+    debug!(
+        "Classified {} as {:?} with duration {:?}",
+        url, kind, duration
+    );
+
+    let max_duration = match guild_id {
+        Some(guild_id) => get_duration_limits(ctx, guild_id).await?.url,
+        None => config.max_duration(),
+    };
+    if let (Some(duration), Some(max_duration)) = (duration, max_duration) {
+        if duration > max_duration {
+            return Ok(Err(format!(
+                "That's {} long, I only play up to {}",
+                format_timestamp(duration),
+                format_timestamp(max_duration)
+            )));
+        }
+    }
+
+    Ok(Ok(kind))
+}
+
+/// Creates the input for a classified URL, seekable wherever the source allows it.
+/// Media that has been played before comes from the cache instead.
+pub async fn url_source(
+    ctx: &Context,
+    url: &str,
+    kind: UrlKind,
+    options: &AudioOptions,
+    lazy: bool,
+) -> Result<Input> {
+    // Live streams never end, so there's nothing to cache
+    if kind != UrlKind::Stream {
+        if let Some(path) = get_cached(ctx, url).await {
+            return filtered_source(path, options, lazy).await;
+        }
+        cache_in_background(ctx.clone(), url.to_owned());
+    }
+
+    match kind {
+        // Resolved through the configured downloader rather than songbird's own youtube-dl call
+        UrlKind::Ytdl => {
+            let downloader = get_downloader(ctx).await?;
+            let allow_private_hosts = get_url_config(ctx).await?.allow_private_hosts;
+            let stream_url = resolve_public_stream_url(&downloader, url, allow_private_hosts)
+                .await
+                .with_context(|| format!("Error streaming {}", url))?
+                .map_err(handle_error)?;
+            filtered_source(stream_url, options, lazy).await
+        }
+        UrlKind::AudioFile | UrlKind::Stream => filtered_source(url, options, lazy).await,
+    }
+}
+
+fn has_audio_extension(url: &str) -> bool {
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or(url);
+
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
+}
+
+/// Asks the downloader how long the video is, None for live streams or if it doesn't know
+async fn probe_ytdl_duration(downloader: &str, url: &str) -> Option<Duration> {
+    let probe = Command::new(downloader)
+        .args(&["--no-playlist", "--get-duration", url])
+        .kill_on_drop(true)
+        .output();
+
+    match timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok(output)) if output.status.success() => {
+            parse_timestamp(String::from_utf8_lossy(&output.stdout).trim())
+                .filter(|duration| !duration.is_zero())
+        }
+        Ok(Ok(_)) => None,
+        Ok(Err(err)) => {
+            warn!("Error running {}: {}", downloader, err);
+            None
+        }
+        Err(_) => {
+            warn!("Looking up the duration of {} timed out", url);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gets_host_of_url() {
+        assert_eq!(
+            get_host("https://www.YouTube.com/watch?v=abc"),
+            Some("www.youtube.com".to_string())
+        );
+        assert_eq!(
+            get_host("http://user:pw@radio.example.org:8000/stream"),
+            Some("radio.example.org".to_string())
+        );
+        assert_eq!(
+            get_host("https://example.org.?q=1"),
+            Some("example.org".to_string())
+        );
+        assert_eq!(get_host("http://[::1]:8080/"), Some("::1".to_string()));
+    }
+
+    #[test]
+    fn gets_no_host_of_invalid_url() {
+        assert_eq!(get_host("ftp://example.org"), None);
+        assert_eq!(get_host("https:///path"), None);
+        assert_eq!(get_host("bruh"), None);
+    }
+
+    #[test]
+    fn matches_domain_and_subdomains() {
+        assert!(matches_domain("youtube.com", "youtube.com"));
+        assert!(matches_domain("www.youtube.com", "youtube.com"));
+        assert!(matches_domain("m.youtube.com", "*.YouTube.com"));
+        assert!(!matches_domain("notyoutube.com", "youtube.com"));
+        assert!(!matches_domain("youtube.com.evil.org", "youtube.com"));
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+        assert!(is_public_ip("100.128.0.1".parse().unwrap()));
+        assert!(is_public_ip("2606:2800:220:1::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_local_addresses() {
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_ip(address.parse().unwrap()), "{}", address);
+        }
+    }
+}
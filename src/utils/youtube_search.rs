@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use anyhow::{Context as AnyhowCtx, Result};
+use serde::Deserialize;
+use tokio::{process::Command, time::timeout};
+
+use super::error::handle_error;
+
+pub const MAX_SEARCH_RESULTS: usize = 5;
+pub const SEARCH_MENU_ID: &str = "yt_search";
+
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub uploader: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+/// One line of youtube-dl's flat playlist output
+#[derive(Deserialize)]
+struct FlatEntry {
+    id: String,
+    title: Option<String>,
+    uploader: Option<String>,
+    duration: Option<f64>,
+}
+
+/// Runs a Youtube search through the youtube-dl compatible downloader
+/// and returns the top results
+pub async fn search_youtube(downloader: &str, query: &str) -> Result<Vec<SearchResult>> {
+    let search = Command::new(downloader)
+        .arg("--flat-playlist")
+        .arg("--dump-json")
+        .arg(format!("ytsearch{}:{}", MAX_SEARCH_RESULTS, query))
+        .kill_on_drop(true)
+        .output();
+
+    let output = timeout(SEARCH_TIMEOUT, search)
+        .await
+        .map_err(|_| handle_error(format!("Searching for {} timed out", query)))?
+        .with_context(|| format!("Error running {}", downloader))?;
+
+    if !output.status.success() {
+        return Err(handle_error(format!(
+            "{} could not search for {}: {}",
+            downloader,
+            query,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    let results = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<FlatEntry>(line).ok())
+        .map(|entry| SearchResult {
+            title: entry.title.unwrap_or_else(|| entry.id.clone()),
+            url: format!("https://www.youtube.com/watch?v={}", entry.id),
+            uploader: entry.uploader,
+            duration: entry
+                .duration
+                .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                .map(Duration::from_secs_f64),
+        })
+        .take(MAX_SEARCH_RESULTS)
+        .collect();
+
+    Ok(results)
+}